@@ -0,0 +1,31 @@
+#!/bin/sh
+# Creates a directory with many entries for benchmarking directory listings:
+#
+#   ./bench-fixture.sh /tmp/huge 1000000
+#   time ./target/release/puppyutils ls /tmp/huge > /dev/null
+#
+# One in every hundred entries is a directory and one a symlink,
+# so listings that need file types have some work to do.
+
+set -eu
+
+if [ $# -lt 1 ] || [ $# -gt 2 ]; then
+    echo "usage: $0 DIR [COUNT]" >&2
+    exit 1
+fi
+
+dir=$1
+count=${2:-1000000}
+
+mkdir -p "$dir"
+cd "$dir"
+
+# plain files, created in batches by xargs rather than one process per file
+seq -f 'file%.0f' 1 "$count" | awk 'NR % 100 != 1 && NR % 100 != 2' | xargs touch
+
+seq -f 'dir%.0f' 1 100 "$count" | xargs mkdir -p
+seq -f 'link%.0f' 2 100 "$count" | while read -r link; do
+    ln -sf "file$((${link#link} + 1))" "$link"
+done
+
+echo "$(ls -U | wc -l) entries in $dir"
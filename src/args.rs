@@ -0,0 +1,79 @@
+use std::{cell::RefCell, collections::VecDeque, env, rc::Rc};
+
+use sap::Parser;
+
+use crate::Result;
+
+struct State {
+    rest: VecDeque<String>,
+    /// the argument the parser took last
+    last: String,
+}
+
+/// The command line for `sap`, which can also hand out the arguments of
+/// options the way `getopt` does. `Parser::value` refuses the ones starting
+/// with `-`, like in `-d '-1 hour'`, and the ones stuck to short options,
+/// like in `-m755`.
+#[derive(Clone)]
+pub struct Args(Rc<RefCell<State>>);
+
+impl Iterator for Args {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let mut state = self.0.borrow_mut();
+        let arg = state.rest.pop_front()?;
+        state.last.clone_from(&arg);
+
+        Some(arg)
+    }
+}
+
+impl Args {
+    pub fn from_env() -> Self {
+        Self(Rc::new(RefCell::new(State {
+            rest: env::args().collect(),
+            last: String::new(),
+        })))
+    }
+
+    /// A parser reading from these arguments
+    pub fn parser(&self) -> Result<Parser<Args>> {
+        Ok(Parser::from_arbitrary(self.clone())?)
+    }
+
+    /// The argument of the option `parser` just returned, whatever it looks
+    /// like. `short` is the name of the option if it was a short one.
+    pub fn value(&self, parser: &mut Parser<Args>, short: char) -> Option<String> {
+        let last = self.0.borrow().last.clone();
+
+        if last.starts_with("--") {
+            // `--option=value`
+            if last.contains('=') {
+                return parser.value();
+            }
+
+            return self.next_arg();
+        }
+
+        let (_, value) = last.split_once(short)?;
+
+        if value.is_empty() {
+            return self.next_arg();
+        }
+
+        // the parser would go on with the rest of `-xVALUE` as options,
+        // so it starts over after it
+        self.0
+            .borrow_mut()
+            .rest
+            .push_front(parser.name().to_owned());
+        *parser = self.parser().ok()?;
+
+        Some(value.to_owned())
+    }
+
+    fn next_arg(&self) -> Option<String> {
+        self.0.borrow_mut().rest.pop_front()
+    }
+}
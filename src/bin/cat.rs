@@ -1,42 +1,330 @@
 use std::{
     fs::File,
-    io::{self, BufReader, BufWriter, Write, stdin, stdout},
+    io::{self, Read, Seek, Write, stdin, stdout},
+    os::unix::fs::MetadataExt,
+    process::exit,
 };
 
-use puppyutils::{Result, cli};
+use puppyutils::{Result, cli, error_message, read_uninterrupted, std_file};
+
+const BUFFER_SIZE: usize = 128 * 1024;
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy)]
+    struct Flags: u8 {
+        const NUMBER           = 1 << 0;
+        const NUMBER_NONBLANK  = 1 << 1;
+        const SQUEEZE_BLANK    = 1 << 2;
+        const SHOW_ENDS        = 1 << 3;
+        const SHOW_TABS        = 1 << 4;
+        const SHOW_NONPRINTING = 1 << 5;
+    }
+}
+
+/// Applies the formatting options byte by byte, its state carries over from
+/// one file to the next so numbering and squeezing continue across files.
+struct Formatter {
+    flags: Flags,
+    line: u64,
+    /// Newlines seen since the last byte that wasn't one, -1 in the middle of a line
+    newlines: i8,
+    /// A carriage return that is shown as `^M` if a newline follows it, with `-E`
+    pending_cr: bool,
+    out: Vec<u8>,
+}
+
+/// Why copying a file stopped, the other files are still
+/// copied after read errors but not after write errors
+enum CopyError {
+    Read(io::Error),
+    Write(io::Error),
+}
+
+impl Formatter {
+    fn new(flags: Flags) -> Self {
+        Self {
+            flags,
+            line: 0,
+            newlines: 0,
+            pending_cr: false,
+            out: Vec::with_capacity(BUFFER_SIZE * 4),
+        }
+    }
+
+    fn copy<R: Read, W: Write>(
+        &mut self,
+        mut reader: R,
+        buf: &mut [u8],
+        out: &mut W,
+    ) -> Result<(), CopyError> {
+        loop {
+            let len = read_uninterrupted(&mut reader, buf).map_err(CopyError::Read)?;
+            if len == 0 {
+                return Ok(());
+            }
+
+            self.format(&buf[..len]);
+
+            out.write_all(&self.out).map_err(CopyError::Write)?;
+            self.out.clear();
+        }
+    }
+
+    fn format(&mut self, input: &[u8]) {
+        let number = self
+            .flags
+            .intersects(Flags::NUMBER | Flags::NUMBER_NONBLANK);
+
+        let len = input.len();
+        let mut i = 0;
+
+        while i < len {
+            let byte = input[i];
+
+            if byte == b'\n' {
+                i += 1;
+                self.newlines += 1;
+
+                // an empty line
+                if self.newlines > 0 {
+                    if self.newlines >= 2 {
+                        self.newlines = 2;
+
+                        if self.flags.contains(Flags::SQUEEZE_BLANK) {
+                            continue;
+                        }
+                    }
+
+                    if self.flags.contains(Flags::NUMBER)
+                        && !self.flags.contains(Flags::NUMBER_NONBLANK)
+                    {
+                        self.push_line_number();
+                    }
+                }
+
+                if self.flags.contains(Flags::SHOW_ENDS) {
+                    if self.pending_cr {
+                        self.out.extend_from_slice(b"^M");
+                        self.pending_cr = false;
+                    }
+
+                    self.out.push(b'$');
+                }
+
+                self.out.push(b'\n');
+                continue;
+            }
+
+            if self.pending_cr {
+                self.out.push(b'\r');
+                self.pending_cr = false;
+            }
+
+            if self.newlines >= 0 && number {
+                self.push_line_number();
+            }
+
+            self.newlines = -1;
+
+            if !is_plain(byte, self.flags) {
+                self.push_byte(byte);
+                i += 1;
+                continue;
+            }
+
+            // bytes that are output as they are get copied in one go
+            let run = input[i..]
+                .iter()
+                .position(|&byte| !is_plain(byte, self.flags))
+                .unwrap_or(len - i);
+
+            self.out.extend_from_slice(&input[i..i + run]);
+            i += run;
+        }
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        let show_tabs = self.flags.contains(Flags::SHOW_TABS);
+
+        if !self.flags.contains(Flags::SHOW_NONPRINTING) {
+            match byte {
+                b'\t' if show_tabs => self.out.extend_from_slice(b"^I"),
+                b'\r' if self.flags.contains(Flags::SHOW_ENDS) => self.pending_cr = true,
+                _ => self.out.push(byte),
+            }
+
+            return;
+        }
+
+        match byte {
+            b'\t' if !show_tabs => self.out.push(b'\t'),
+            0..32 => self.out.extend_from_slice(&[b'^', byte + 64]),
+            32..127 => self.out.push(byte),
+            127 => self.out.extend_from_slice(b"^?"),
+            128..160 => self
+                .out
+                .extend_from_slice(&[b'M', b'-', b'^', byte - 128 + 64]),
+            160..255 => self.out.extend_from_slice(&[b'M', b'-', byte - 128]),
+            255 => self.out.extend_from_slice(b"M-^?"),
+        }
+    }
+
+    /// Numbers are right aligned in 6 columns and followed by a tab, like GNU
+    fn push_line_number(&mut self) {
+        self.line += 1;
+
+        let mut buf = itoa::Buffer::new();
+        let number = buf.format(self.line);
+
+        self.out.extend(std::iter::repeat_n(
+            b' ',
+            6usize.saturating_sub(number.len()),
+        ));
+        self.out.extend_from_slice(number.as_bytes());
+        self.out.push(b'\t');
+    }
+
+    /// Outputs what is still held back once all files are read
+    fn finish<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        if self.pending_cr {
+            self.pending_cr = false;
+            out.write_all(b"\r")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `byte` is output unchanged in the middle of a line
+#[inline]
+fn is_plain(byte: u8, flags: Flags) -> bool {
+    match byte {
+        b'\n' => false,
+        b'\t' => !flags.contains(Flags::SHOW_TABS),
+        b'\r' if flags.contains(Flags::SHOW_ENDS) => false,
+        32..127 => true,
+        _ => !flags.contains(Flags::SHOW_NONPRINTING),
+    }
+}
+
+/// Copies without formatting, writing whatever was read right away
+/// so `cat` on a terminal doesn't wait for more input
+fn copy<R: Read, W: Write>(mut reader: R, buf: &mut [u8], out: &mut W) -> Result<(), CopyError> {
+    loop {
+        let len = read_uninterrupted(&mut reader, buf).map_err(CopyError::Read)?;
+        if len == 0 {
+            return Ok(());
+        }
+
+        out.write_all(&buf[..len]).map_err(CopyError::Write)?;
+    }
+}
+
+/// Copies the file at `path`, "-" meaning stdin. Like GNU, a regular file that
+/// is also the output is refused unless it was read to the end already,
+/// or `cat file >> file` would never stop growing `file`.
+fn cat<W: Write>(
+    path: &str,
+    output: Option<(u64, u64)>,
+    formatter: Option<&mut Formatter>,
+    buf: &mut [u8],
+    out: &mut W,
+) -> Result<(), CopyError> {
+    let mut file = if path == "-" {
+        std_file(stdin())
+    } else {
+        File::open(path)
+    }
+    .map_err(CopyError::Read)?;
+
+    let meta = file.metadata().map_err(CopyError::Read)?;
+
+    if output == Some((meta.dev(), meta.ino()))
+        && file.stream_position().map_or(true, |pos| pos < meta.size())
+    {
+        return Err(CopyError::Read(io::Error::other(
+            "input file is output file",
+        )));
+    }
+
+    match formatter {
+        Some(formatter) => formatter.copy(file, buf, out),
+        None => copy(file, buf, out),
+    }
+}
 
 pub fn main() -> Result {
     let mut stdout = stdout();
     let mut files = Vec::new();
+    let mut flags = Flags::empty();
 
     cli! {
         "cat", stdout, #error
+        Short('A') | Long("show-all") => {
+            flags |= Flags::SHOW_NONPRINTING | Flags::SHOW_ENDS | Flags::SHOW_TABS
+        }
+        Short('b') | Long("number-nonblank") => flags |= Flags::NUMBER_NONBLANK
+        Short('e') => flags |= Flags::SHOW_NONPRINTING | Flags::SHOW_ENDS
+        Short('E') | Long("show-ends") => flags |= Flags::SHOW_ENDS
+        Short('n') | Long("number") => flags |= Flags::NUMBER
+        Short('s') | Long("squeeze-blank") => flags |= Flags::SQUEEZE_BLANK
+        Short('t') => flags |= Flags::SHOW_NONPRINTING | Flags::SHOW_TABS
+        Short('T') | Long("show-tabs") => flags |= Flags::SHOW_TABS
+        Short('u') => {}
+        Short('v') | Long("show-nonprinting") => flags |= Flags::SHOW_NONPRINTING
         Value(value) => {
             files.push(value.into_owned());
         }
+        Stdio => files.push("-".to_owned())
     };
 
-    let mut stdout = BufWriter::new(stdout);
-
-    // FIXME: so much repetition, can we do better?
     if files.is_empty() {
-        let mut stdin = BufReader::new(stdin());
-        io::copy(&mut stdin, &mut stdout)?;
-    } else {
-        for file_path in files {
-            // "-" means stdin
-            if file_path == "-" {
-                // TODO: maybe the handling for - should be at the arg parser stage?
-                let mut stdin = BufReader::new(stdin());
-                io::copy(&mut stdin, &mut stdout)?;
-            } else {
-                let mut reader = BufReader::new(File::open(file_path)?); // Is it fine to error here? or should we keep going
-                io::copy(&mut reader, &mut stdout)?;
+        files.push("-".to_owned());
+    }
+
+    let mut out = match std_file(&stdout) {
+        Ok(out) => out,
+        Err(err) => {
+            eprintln!("cat: standard output: {}", error_message(&err));
+            exit(1);
+        }
+    };
+
+    // only regular files can be caught being read back in
+    let output = out
+        .metadata()
+        .ok()
+        .filter(|meta| meta.is_file())
+        .map(|meta| (meta.dev(), meta.ino()));
+
+    let mut buf = vec![0; BUFFER_SIZE];
+    let mut formatter = (!flags.is_empty()).then(|| Formatter::new(flags));
+    let mut status = 0;
+
+    for file_path in files {
+        match cat(&file_path, output, formatter.as_mut(), &mut buf, &mut out) {
+            Ok(()) => {}
+            Err(CopyError::Read(err)) => {
+                eprintln!("cat: {file_path}: {}", error_message(&err));
+                status = 1;
+            }
+            Err(CopyError::Write(err)) => {
+                eprintln!("cat: write error: {}", error_message(&err));
+                exit(1);
             }
         }
     }
 
-    stdout.flush()?;
+    if let Some(formatter) = &mut formatter
+        && let Err(err) = formatter.finish(&mut out)
+    {
+        eprintln!("cat: write error: {}", error_message(&err));
+        exit(1);
+    }
+
+    if status != 0 {
+        exit(status);
+    }
 
     Ok(())
 }
@@ -0,0 +1,522 @@
+use std::{
+    env,
+    io::{self, Write},
+    os::unix::ffi::OsStrExt,
+};
+
+use super::entry::{Entry, S_IFBLK, S_IFCHR, S_IFDIR, S_IFIFO, S_IFLNK, S_IFMT, S_IFREG, S_IFSOCK};
+
+const S_ISUID: u32 = 0o4000;
+const S_ISGID: u32 = 0o2000;
+const S_ISVTX: u32 = 0o1000;
+const S_IWOTH: u32 = 0o0002;
+const S_IXUGO: u32 = 0o0111;
+
+/// The sequences that can be set in `LS_COLORS` through a two-letter key
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub(crate) enum Indicator {
+    Left,
+    Right,
+    End,
+    Reset,
+    Norm,
+    File,
+    Dir,
+    Link,
+    Fifo,
+    Sock,
+    Blk,
+    Chr,
+    Missing,
+    Orphan,
+    Exec,
+    // doors don't exist on Linux
+    #[allow(dead_code)]
+    Door,
+    SetUid,
+    SetGid,
+    Sticky,
+    OtherWritable,
+    StickyOtherWritable,
+    Cap,
+    MultiHardlink,
+    ClrToEol,
+}
+
+const INDICATOR_COUNT: usize = Indicator::ClrToEol as usize + 1;
+
+const INDICATOR_NAMES: [&[u8; 2]; INDICATOR_COUNT] = [
+    b"lc", b"rc", b"ec", b"rs", b"no", b"fi", b"di", b"ln", b"pi", b"so", b"bd", b"cd", b"mi",
+    b"or", b"ex", b"do", b"su", b"sg", b"st", b"ow", b"tw", b"ca", b"mh", b"cl",
+];
+
+const DEFAULT_INDICATORS: [Option<&[u8]>; INDICATOR_COUNT] = [
+    Some(b"\x1b["),
+    Some(b"m"),
+    None,
+    Some(b"0"),
+    None,
+    None,
+    Some(b"01;34"),
+    Some(b"01;36"),
+    Some(b"33"),
+    Some(b"01;35"),
+    Some(b"01;33"),
+    Some(b"01;33"),
+    None,
+    None,
+    Some(b"01;32"),
+    Some(b"01;35"),
+    Some(b"37;41"),
+    Some(b"30;43"),
+    Some(b"37;44"),
+    Some(b"34;42"),
+    Some(b"30;42"),
+    None,
+    None,
+    Some(b"\x1b[K"),
+];
+
+/// Terminals known to support colors when `LS_COLORS` is not set,
+/// as listed in the default `dircolors` database.
+const TERMS: &[&[u8]] = &[
+    b"Eterm",
+    b"ansi",
+    b"*color*",
+    b"con[0-9]*x[0-9]*",
+    b"cons25",
+    b"console",
+    b"cygwin",
+    b"*direct*",
+    b"dtterm",
+    b"gnome",
+    b"hurd",
+    b"jfbterm",
+    b"konsole",
+    b"kterm",
+    b"linux",
+    b"linux-c",
+    b"mlterm",
+    b"putty",
+    b"rxvt*",
+    b"screen*",
+    b"st",
+    b"terminator",
+    b"tmux*",
+    b"vt100",
+    b"xterm*",
+];
+
+/// A color chosen for a file name
+#[derive(Clone, Copy)]
+pub(crate) enum Color {
+    Indicator(Indicator),
+    Extension(usize),
+}
+
+struct Extension {
+    suffix: Vec<u8>,
+    sequence: Vec<u8>,
+}
+
+pub(crate) struct Colors {
+    indicators: [Option<Vec<u8>>; INDICATOR_COUNT],
+    /// later definitions come first, so they take precedence
+    extensions: Vec<Extension>,
+    /// set by `ln=target`, symlinks are colored like the file they point to
+    symlink_as_referent: bool,
+    /// whether any sequence was written yet
+    used: bool,
+}
+
+impl Colors {
+    /// Reads the colors from `LS_COLORS`, falling back to the built-in ones.
+    /// Returns `None` when colors should not be used after all.
+    pub(crate) fn from_env(program: &str) -> Option<Self> {
+        let mut colors = Self {
+            indicators: DEFAULT_INDICATORS.map(|sequence| sequence.map(<[u8]>::to_vec)),
+            extensions: Vec::new(),
+            symlink_as_referent: false,
+            used: false,
+        };
+
+        match env::var_os("LS_COLORS") {
+            Some(spec) if !spec.is_empty() => {
+                if !colors.parse(spec.as_bytes(), program) {
+                    eprintln!("{program}: unparsable value for LS_COLORS environment variable");
+                    return None;
+                }
+            }
+            _ => {
+                let color_term = env::var_os("COLORTERM").is_some_and(|term| !term.is_empty());
+                if !color_term && !known_term_type() {
+                    return None;
+                }
+            }
+        }
+
+        colors.symlink_as_referent = colors.indicator(Indicator::Link) == b"target";
+
+        Some(colors)
+    }
+
+    fn parse(&mut self, mut spec: &[u8], program: &str) -> bool {
+        loop {
+            match spec {
+                [] => break,
+                [b':', rest @ ..] => spec = rest,
+                [b'*', rest @ ..] => {
+                    let Some((suffix, rest)) = funky_string(rest, true) else {
+                        return false;
+                    };
+                    let [b'=', rest @ ..] = rest else {
+                        return false;
+                    };
+                    let Some((sequence, rest)) = funky_string(rest, false) else {
+                        return false;
+                    };
+
+                    self.extensions.insert(0, Extension { suffix, sequence });
+                    spec = rest;
+                }
+                [first, second, b'=', rest @ ..] => {
+                    let label = [*first, *second];
+                    let parsed = INDICATOR_NAMES
+                        .iter()
+                        .position(|name| **name == label)
+                        .and_then(|index| Some((index, funky_string(rest, false)?)));
+
+                    // like GNU, a bad value is reported as a bad prefix too
+                    let Some((index, (sequence, rest))) = parsed else {
+                        eprintln!(
+                            "{program}: unrecognized prefix: '{}'",
+                            String::from_utf8_lossy(&label)
+                        );
+                        return false;
+                    };
+
+                    self.indicators[index] = Some(sequence);
+                    spec = rest;
+                }
+                _ => return false,
+            }
+        }
+
+        true
+    }
+
+    fn indicator(&self, indicator: Indicator) -> &[u8] {
+        self.indicators[indicator as usize]
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    fn sequence(&self, color: Color) -> &[u8] {
+        match color {
+            Color::Indicator(indicator) => self.indicator(indicator),
+            Color::Extension(index) => &self.extensions[index].sequence,
+        }
+    }
+
+    pub(crate) fn is_colored(&self, indicator: Indicator) -> bool {
+        !matches!(self.indicator(indicator), b"" | b"0" | b"00")
+    }
+
+    /// Whether symlinks are colored like the file they point to
+    #[inline]
+    pub(crate) fn symlink_as_referent(&self) -> bool {
+        self.symlink_as_referent
+    }
+
+    /// Whether symlink targets have to be inspected to pick colors
+    pub(crate) fn needs_link_targets(&self, long: bool) -> bool {
+        self.is_colored(Indicator::Orphan)
+            || (self.is_colored(Indicator::Exec) && self.symlink_as_referent)
+            || (self.is_colored(Indicator::Missing) && long)
+    }
+
+    /// Picks the color of an entry's name, or of its symlink target if `target` is set
+    pub(crate) fn color_of(&self, entry: &Entry, target: bool) -> Option<Color> {
+        let (name, mode, link_ok) = if target {
+            (
+                entry.link_target.as_deref().unwrap_or_default(),
+                entry.link_meta.as_ref().map_or(0, |meta| meta.mode),
+                Some(entry.link_meta.is_some()),
+            )
+        } else {
+            let meta = match &entry.link_meta {
+                Some(meta) if self.symlink_as_referent => Some(meta),
+                _ => entry.meta.as_ref(),
+            };
+
+            (
+                entry.name.as_os_str(),
+                meta.map_or(0, |meta| meta.mode),
+                None,
+            )
+        };
+
+        let mut indicator = if link_ok == Some(false) && self.is_colored(Indicator::Missing) {
+            Indicator::Missing
+        } else if entry.meta.is_none() {
+            file_type_indicator(entry.file_type)
+        } else {
+            match mode & S_IFMT {
+                S_IFREG => {
+                    let nlink = entry.meta.as_ref().map_or(1, |meta| meta.nlink);
+
+                    if mode & S_ISUID != 0 && self.is_colored(Indicator::SetUid) {
+                        Indicator::SetUid
+                    } else if mode & S_ISGID != 0 && self.is_colored(Indicator::SetGid) {
+                        Indicator::SetGid
+                    } else if !target && entry.has_capability && self.is_colored(Indicator::Cap) {
+                        Indicator::Cap
+                    } else if mode & S_IXUGO != 0 && self.is_colored(Indicator::Exec) {
+                        Indicator::Exec
+                    } else if nlink > 1 && self.is_colored(Indicator::MultiHardlink) {
+                        Indicator::MultiHardlink
+                    } else {
+                        Indicator::File
+                    }
+                }
+                S_IFDIR => {
+                    let sticky = mode & S_ISVTX != 0;
+                    let other_writable = mode & S_IWOTH != 0;
+
+                    if sticky && other_writable && self.is_colored(Indicator::StickyOtherWritable) {
+                        Indicator::StickyOtherWritable
+                    } else if other_writable && self.is_colored(Indicator::OtherWritable) {
+                        Indicator::OtherWritable
+                    } else if sticky && self.is_colored(Indicator::Sticky) {
+                        Indicator::Sticky
+                    } else {
+                        Indicator::Dir
+                    }
+                }
+                file_type => file_type_indicator(file_type),
+            }
+        };
+
+        if indicator == Indicator::File {
+            let name = name.as_bytes();
+            let extension = self.extensions.iter().position(|extension| {
+                let suffix = &extension.suffix;
+
+                suffix.len() <= name.len()
+                    && name[name.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+            });
+
+            if let Some(index) = extension {
+                return Some(Color::Extension(index));
+            }
+        }
+
+        if indicator == Indicator::Link
+            && entry.link_meta.is_none()
+            && (self.symlink_as_referent || self.is_colored(Indicator::Orphan))
+        {
+            indicator = Indicator::Orphan;
+        }
+
+        self.indicators[indicator as usize]
+            .is_some()
+            .then_some(Color::Indicator(indicator))
+    }
+
+    fn put<W: Write>(&mut self, out: &mut W, color: Color) -> io::Result<()> {
+        if !self.used {
+            self.used = true;
+            self.prep_non_filename_text(out)?;
+        }
+
+        out.write_all(self.sequence(color))
+    }
+
+    fn restore_default_color<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        self.put(out, Color::Indicator(Indicator::Left))?;
+        self.put(out, Color::Indicator(Indicator::Right))
+    }
+
+    pub(crate) fn print_color<W: Write>(&mut self, out: &mut W, color: Color) -> io::Result<()> {
+        if self.is_colored(Indicator::Norm) {
+            self.restore_default_color(out)?;
+        }
+
+        self.put(out, Color::Indicator(Indicator::Left))?;
+        self.put(out, color)?;
+        self.put(out, Color::Indicator(Indicator::Right))
+    }
+
+    /// Switches back to the normal color after a file name
+    pub(crate) fn prep_non_filename_text<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        if self.indicators[Indicator::End as usize].is_some() {
+            self.put(out, Color::Indicator(Indicator::End))
+        } else {
+            self.put(out, Color::Indicator(Indicator::Left))?;
+            self.put(out, Color::Indicator(Indicator::Reset))?;
+            self.put(out, Color::Indicator(Indicator::Right))
+        }
+    }
+
+    pub(crate) fn set_normal_color<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        if self.is_colored(Indicator::Norm) {
+            self.put(out, Color::Indicator(Indicator::Left))?;
+            self.put(out, Color::Indicator(Indicator::Norm))?;
+            self.put(out, Color::Indicator(Indicator::Right))?;
+        }
+
+        Ok(())
+    }
+
+    /// Clears the rest of the line, for names whose color could spill over a wrapped line
+    pub(crate) fn clear_to_eol<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        self.put(out, Color::Indicator(Indicator::ClrToEol))
+    }
+
+    /// Restores the terminal's colors once everything was printed
+    pub(crate) fn finish<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let default_sequence =
+            self.indicator(Indicator::Left) == b"\x1b[" && self.indicator(Indicator::Right) == b"m";
+
+        if self.used && !default_sequence {
+            self.restore_default_color(out)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn file_type_indicator(file_type: u32) -> Indicator {
+    match file_type {
+        S_IFREG => Indicator::File,
+        S_IFDIR => Indicator::Dir,
+        S_IFLNK => Indicator::Link,
+        S_IFIFO => Indicator::Fifo,
+        S_IFSOCK => Indicator::Sock,
+        S_IFBLK => Indicator::Blk,
+        S_IFCHR => Indicator::Chr,
+        _ => Indicator::Orphan,
+    }
+}
+
+/// Decodes a value of `LS_COLORS`, which may contain backslash and caret escapes.
+/// Stops at a `:`, or at a `=` if `equals_end` is set, returning the rest.
+fn funky_string(mut spec: &[u8], equals_end: bool) -> Option<(Vec<u8>, &[u8])> {
+    let mut out = Vec::new();
+
+    loop {
+        match spec {
+            [] | [b':', ..] => break,
+            [b'=', ..] if equals_end => break,
+            [b'\\', rest @ ..] => {
+                let (&escaped, rest) = rest.split_first()?;
+                spec = rest;
+
+                let byte = match escaped {
+                    b'0'..=b'7' => {
+                        let mut num = escaped - b'0';
+                        while let [digit @ b'0'..=b'7', rest @ ..] = spec {
+                            num = (num << 3).wrapping_add(digit - b'0');
+                            spec = rest;
+                        }
+                        num
+                    }
+                    b'x' | b'X' => {
+                        let mut num = 0u8;
+                        while let [digit, rest @ ..] = spec
+                            && let Some(value) = (*digit as char).to_digit(16)
+                        {
+                            num = (num << 4).wrapping_add(value as u8);
+                            spec = rest;
+                        }
+                        num
+                    }
+                    b'a' => 0x07,
+                    b'b' => 0x08,
+                    b'e' => 0x1b,
+                    b'f' => 0x0c,
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    b'v' => 0x0b,
+                    b'?' => 0x7f,
+                    b'_' => b' ',
+                    other => other,
+                };
+                out.push(byte);
+            }
+            [b'^', rest @ ..] => match rest {
+                [caret @ b'@'..=b'~', rest @ ..] => {
+                    out.push(caret & 0o37);
+                    spec = rest;
+                }
+                // like GNU, the `?` is not consumed here
+                [b'?', ..] => {
+                    out.push(0x7f);
+                    spec = rest;
+                }
+                _ => return None,
+            },
+            [byte, rest @ ..] => {
+                out.push(*byte);
+                spec = rest;
+            }
+        }
+    }
+
+    Some((out, spec))
+}
+
+fn known_term_type() -> bool {
+    let Some(term) = env::var_os("TERM") else {
+        return false;
+    };
+
+    !term.is_empty()
+        && TERMS
+            .iter()
+            .any(|pattern| fnmatch(pattern, term.as_bytes()))
+}
+
+/// Matches `name` against a shell wildcard pattern supporting `*`, `?` and `[...]`
+pub(crate) fn fnmatch(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| fnmatch(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && fnmatch(rest, &name[1..]),
+        Some((b'[', rest)) => {
+            let Some((&byte, name_rest)) = name.split_first() else {
+                return false;
+            };
+
+            let (negate, class) = match rest {
+                [b'!' | b'^', class @ ..] => (true, class),
+                class => (false, class),
+            };
+
+            // a `]` right after the opening bracket is part of the set
+            let Some(end) = class.iter().skip(1).position(|&b| b == b']') else {
+                return byte == b'[' && fnmatch(rest, name_rest);
+            };
+            let (set, after) = (&class[..end + 1], &class[end + 2..]);
+
+            let mut matched = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == b'-' {
+                    matched |= (set[i]..=set[i + 2]).contains(&byte);
+                    i += 3;
+                } else {
+                    matched |= set[i] == byte;
+                    i += 1;
+                }
+            }
+
+            matched != negate && fnmatch(after, name_rest)
+        }
+        Some((b'\\', [escaped, rest @ ..])) | Some((escaped, rest)) => {
+            name.first() == Some(escaped) && fnmatch(rest, &name[1..])
+        }
+    }
+}
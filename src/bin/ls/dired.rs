@@ -0,0 +1,87 @@
+use std::io::{self, Write};
+
+/// Output that keeps track of how many bytes went through it, for `--dired`.
+/// Colors and hyperlinks are written to `inner` directly, so they are not counted.
+pub(crate) struct Output<W: Write> {
+    pub(crate) inner: W,
+    pub(crate) pos: usize,
+}
+
+impl<W: Write> Output<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self { inner, pos: 0 }
+    }
+}
+
+impl<W: Write> Write for Output<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.pos += written;
+
+        Ok(written)
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.inner.write_all(buf)?;
+        self.pos += buf.len();
+
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Which offsets a printed name is recorded in
+#[derive(Clone, Copy)]
+pub(crate) enum DiredList {
+    Names,
+    Subdirs,
+}
+
+/// Byte offsets of the file names and directory headers printed with `--dired`
+#[derive(Default)]
+pub(crate) struct Dired {
+    pub(crate) names: Vec<usize>,
+    pub(crate) subdirs: Vec<usize>,
+}
+
+impl Dired {
+    pub(crate) fn push(&mut self, list: DiredList, start: usize, end: usize) {
+        let positions = match list {
+            DiredList::Names => &mut self.names,
+            DiredList::Subdirs => &mut self.subdirs,
+        };
+
+        positions.extend([start, end]);
+    }
+
+    /// Writes the trailer Emacs reads the offsets from
+    pub(crate) fn dump<W: Write>(&self, out: &mut W, quoting_style: &str) -> io::Result<()> {
+        dump_positions(out, b"//DIRED//", &self.names)?;
+        dump_positions(out, b"//SUBDIRED//", &self.subdirs)?;
+
+        out.write_all(b"//DIRED-OPTIONS// --quoting-style=")?;
+        out.write_all(quoting_style.as_bytes())?;
+        out.write_all(b"\n")
+    }
+}
+
+fn dump_positions<W: Write>(out: &mut W, prefix: &[u8], positions: &[usize]) -> io::Result<()> {
+    if positions.is_empty() {
+        return Ok(());
+    }
+
+    let mut buf = itoa::Buffer::new();
+
+    out.write_all(prefix)?;
+    for &pos in positions {
+        out.write_all(b" ")?;
+        out.write_all(buf.format(pos).as_bytes())?;
+    }
+    out.write_all(b"\n")
+}
@@ -0,0 +1,315 @@
+use std::{
+    ffi::{OsStr, OsString},
+    io,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+
+use super::{
+    options::{Dereference, TimeField},
+    stat::{self, STATX_MODE, STATX_TYPE, Stat},
+    xattr,
+};
+
+pub(crate) const S_IFMT: u32 = 0o170000;
+pub(crate) const S_IFSOCK: u32 = 0o140000;
+pub(crate) const S_IFLNK: u32 = 0o120000;
+pub(crate) const S_IFREG: u32 = 0o100000;
+pub(crate) const S_IFBLK: u32 = 0o060000;
+pub(crate) const S_IFDIR: u32 = 0o040000;
+pub(crate) const S_IFCHR: u32 = 0o020000;
+pub(crate) const S_IFIFO: u32 = 0o010000;
+
+/// A file as gathered from the command line or a directory listing
+pub(crate) struct Entry {
+    /// name as it should be printed
+    pub(crate) name: OsString,
+
+    /// result of `lstat` (or `stat` when following links), `None` if it
+    /// could not be obtained or nothing shown needed it
+    pub(crate) meta: Option<Stat>,
+
+    /// the `S_IF*` bits of the file type, known from the directory entry
+    /// even when `meta` is missing, 0 if it is unknown
+    pub(crate) file_type: u32,
+
+    /// inode number, 0 if it is unknown
+    pub(crate) ino: u64,
+
+    /// target of the symlink, only read when it will be printed
+    pub(crate) link_target: Option<OsString>,
+
+    /// result of `stat` on the symlink target, if it was read
+    pub(crate) link_meta: Option<Stat>,
+
+    /// canonical name used for `--hyperlink`
+    pub(crate) absolute_name: Option<OsString>,
+
+    /// SELinux security context, `None` if the file has none or it wasn't read
+    pub(crate) context: Option<Vec<u8>>,
+
+    /// whether the file has an ACL beyond its permission bits
+    pub(crate) has_acl: bool,
+
+    /// whether the file has capabilities, only checked when they are colored
+    pub(crate) has_capability: bool,
+
+    /// whether the entry was given as an argument
+    pub(crate) command_line: bool,
+}
+
+/// What to gather about each file besides what its directory tells
+#[derive(Clone, Copy)]
+pub(crate) struct Gobble {
+    /// which symlinks to show the file they point to for, instead of the symlinks themselves
+    pub(crate) deref: Dereference,
+
+    /// the fields `statx` is asked for
+    pub(crate) mask: u32,
+
+    /// `stat` all files, arguments always are
+    pub(crate) stat_all: bool,
+
+    /// `stat` the files found in directories whose type is unknown
+    pub(crate) stat_unknown: bool,
+
+    /// `stat` directories, for colors that depend on their mode
+    pub(crate) stat_dirs: bool,
+
+    /// `stat` regular files, for colors or indicators that depend on their mode
+    pub(crate) stat_files: bool,
+
+    /// `stat` symlinks, when what they point to matters
+    pub(crate) stat_links: bool,
+
+    /// read the target of symlinks
+    pub(crate) read_link: bool,
+
+    /// also `stat` the target of symlinks, only done along with `read_link`
+    pub(crate) stat_link: bool,
+
+    /// read the security context
+    pub(crate) context: bool,
+
+    /// look for ACLs
+    pub(crate) acl: bool,
+
+    /// look for capabilities
+    pub(crate) capability: bool,
+}
+
+impl Entry {
+    /// Gathers a file, `file_type` and `ino` are what its directory
+    /// entry tells, 0 when they are unknown.
+    pub(crate) fn gobble(
+        name: OsString,
+        path: &Path,
+        file_type: u32,
+        ino: u64,
+        opts: Gobble,
+        command_line: bool,
+    ) -> (Self, Option<io::Error>) {
+        let mut entry = Self {
+            name,
+            meta: None,
+            file_type,
+            ino,
+            link_target: None,
+            link_meta: None,
+            absolute_name: None,
+            context: None,
+            has_acl: false,
+            has_capability: false,
+            command_line,
+        };
+
+        let needs_stat = command_line
+            || opts.stat_all
+            || match file_type {
+                0 => opts.stat_unknown,
+                S_IFDIR => opts.stat_dirs,
+                S_IFREG => opts.stat_files,
+                S_IFLNK => opts.stat_links,
+                _ => false,
+            };
+
+        if !needs_stat {
+            return (entry, None);
+        }
+
+        let mask = opts.mask;
+        let meta = match opts.deref {
+            Dereference::Always => stat::statx(path, true, mask),
+            Dereference::CommandLineArguments if command_line => stat::statx(path, true, mask),
+            Dereference::CommandLineSymlinkToDir if command_line => {
+                match stat::statx(path, true, mask) {
+                    Ok(meta) if meta.is_dir() => Ok(meta),
+                    // a dangling or looping symlink is still listed as itself
+                    Err(err) if !matches!(err.raw_os_error(), Some(libc::ENOENT | libc::ELOOP)) => {
+                        Err(err)
+                    }
+                    _ => stat::statx(path, false, mask),
+                }
+            }
+            _ => stat::statx(path, false, mask),
+        };
+
+        let meta = match meta {
+            Ok(meta) => meta,
+            Err(err) => {
+                entry.ino = 0;
+                return (entry, Some(err));
+            }
+        };
+
+        entry.file_type = meta.file_type();
+        entry.ino = meta.ino;
+
+        if opts.read_link && meta.is_symlink() {
+            entry.link_target = std::fs::read_link(path)
+                .ok()
+                .map(|target| target.into_os_string());
+            entry.link_meta = entry
+                .link_target
+                .as_ref()
+                .filter(|_| opts.stat_link)
+                .and_then(|_| stat::statx(path, true, STATX_TYPE | STATX_MODE).ok());
+        }
+
+        entry.meta = Some(meta);
+
+        if opts.context || opts.acl || opts.capability {
+            entry.read_xattrs(path, opts);
+        }
+
+        (entry, None)
+    }
+
+    /// Fills in what is kept in extended attributes, failures are taken as
+    /// the file not having them, `ls` shouldn't fail on file systems without them.
+    fn read_xattrs(&mut self, path: &Path, opts: Gobble) {
+        let Some(meta) = &self.meta else {
+            return;
+        };
+
+        // the attributes of whatever `meta` describes
+        let follow = !meta.is_symlink();
+
+        // asked for directly, tmpfs and kernels without an SELinux policy
+        // leave the context out of `listxattr` but still return it, while
+        // `ENODATA` and `ENOTSUP` mean there's none
+        if opts.context {
+            self.context = xattr::get(path, xattr::SELINUX, follow)
+                .ok()
+                .map(|mut context| {
+                    if context.last() == Some(&0) {
+                        context.pop();
+                    }
+                    context
+                });
+        }
+
+        if !opts.acl && !opts.capability {
+            return;
+        }
+
+        let Ok(names) = xattr::list(path, follow) else {
+            return;
+        };
+
+        if opts.acl && follow {
+            self.has_acl = (xattr::contains(&names, xattr::POSIX_ACL_ACCESS)
+                && xattr::get(path, xattr::POSIX_ACL_ACCESS, follow)
+                    .is_ok_and(|acl| xattr::is_nontrivial_acl(&acl)))
+                || (meta.is_dir() && xattr::contains(&names, xattr::POSIX_ACL_DEFAULT));
+        }
+
+        if opts.capability && meta.is_file() {
+            self.has_capability = xattr::contains(&names, xattr::CAPABILITY);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_dir(&self) -> bool {
+        self.file_type == S_IFDIR
+    }
+
+    #[inline]
+    pub(crate) fn is_linked_dir(&self) -> bool {
+        self.is_dir() || self.link_meta.as_ref().is_some_and(Stat::is_dir)
+    }
+
+    #[inline]
+    pub(crate) fn mode(&self) -> Option<u32> {
+        self.meta.as_ref().map(|meta| meta.mode)
+    }
+
+    #[inline]
+    pub(crate) fn size(&self) -> u64 {
+        self.meta.as_ref().map_or(0, |meta| meta.size)
+    }
+
+    #[inline]
+    pub(crate) fn blocks(&self) -> u64 {
+        self.meta.as_ref().map_or(0, |meta| meta.blocks)
+    }
+
+    /// The chosen timestamp as seconds and nanoseconds,
+    /// `None` if it is unknown (birth time isn't available everywhere).
+    pub(crate) fn time(&self, field: TimeField) -> Option<(i64, i64)> {
+        let meta = self.meta.as_ref()?;
+
+        match field {
+            TimeField::Modification => Some(meta.mtime),
+            TimeField::Access => Some(meta.atime),
+            TimeField::Change => Some(meta.ctime),
+            TimeField::Birth => meta.btime,
+        }
+    }
+
+    /// The character shown after the mode in long listings,
+    /// `.` when the file only has a security context
+    pub(crate) fn acl_marker(&self) -> Option<u8> {
+        let labeled = self
+            .context
+            .as_deref()
+            .is_some_and(|context| context != b"unlabeled");
+
+        if self.has_acl {
+            Some(b'+')
+        } else if labeled {
+            Some(b'.')
+        } else {
+            None
+        }
+    }
+
+    /// Whether the name is `.` or `..`, which are never recursed into
+    #[inline]
+    pub(crate) fn is_dot_or_dotdot(&self) -> bool {
+        matches!(self.name.as_bytes(), b"." | b"..")
+    }
+}
+
+/// Path of a file found in `dir`, leaving out the directory if it is `.`
+pub(crate) fn attach(dir: &OsStr, name: &OsStr) -> PathBuf {
+    if dir == "." {
+        PathBuf::from(name)
+    } else {
+        Path::new(dir).join(name)
+    }
+}
+
+/// Joins a directory and a file name the way GNU's `file_name_concat` does
+pub(crate) fn concat(dir: &OsStr, name: &OsStr) -> OsString {
+    let dir = dir.as_bytes();
+    let mut path = Vec::with_capacity(dir.len() + name.len() + 1);
+
+    path.extend_from_slice(dir);
+    if !dir.ends_with(b"/") {
+        path.push(b'/');
+    }
+    path.extend_from_slice(name.as_bytes());
+
+    OsString::from_vec(path)
+}
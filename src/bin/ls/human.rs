@@ -0,0 +1,306 @@
+const POWER_LETTERS: &[u8] = b"KMGTPEZYRQ";
+
+/// Describes how a block count should be scaled when printed
+#[derive(Clone, Copy)]
+pub(crate) struct HumanOpts {
+    /// pick the largest power that keeps the value under `base`
+    pub(crate) autoscale: bool,
+    /// 1024 or 1000
+    pub(crate) base: u64,
+    /// append the letter of the power, e.g. `K`
+    pub(crate) suffix: bool,
+    /// append `B` (`iB` for powers of 1024) after the letter
+    pub(crate) bytes: bool,
+}
+
+impl HumanOpts {
+    pub(crate) const EXACT: Self = Self {
+        autoscale: false,
+        base: 1024,
+        suffix: false,
+        bytes: false,
+    };
+
+    /// `-h`
+    pub(crate) const HUMAN: Self = Self {
+        autoscale: true,
+        base: 1024,
+        suffix: true,
+        bytes: false,
+    };
+
+    /// `--si`
+    pub(crate) const SI: Self = Self {
+        autoscale: true,
+        base: 1000,
+        suffix: true,
+        bytes: false,
+    };
+}
+
+/// Why a `--block-size` value was rejected
+pub(crate) enum BlockSizeError {
+    Invalid,
+    InvalidSuffix,
+    TooLarge,
+}
+
+/// Parses a block size like GNU's `human_options`, e.g. `1K`, `MiB`, `si` or `'1`,
+/// returning how to print sizes and the size of the unit.
+pub(crate) fn parse_block_size(spec: &str) -> Result<(HumanOpts, u64), BlockSizeError> {
+    // the thousands separator asked for by a leading quote is empty in the C locale
+    let spec = spec.strip_prefix('\'').unwrap_or(spec);
+
+    if !spec.is_empty() && "human-readable".starts_with(spec) {
+        return Ok((HumanOpts::HUMAN, 1));
+    }
+
+    if !spec.is_empty() && "si".starts_with(spec) {
+        return Ok((HumanOpts::SI, 1));
+    }
+
+    if spec.is_empty() {
+        return Err(BlockSizeError::Invalid);
+    }
+
+    let digits = spec.bytes().take_while(u8::is_ascii_digit).count();
+    let (number, suffix) = spec.split_at(digits);
+
+    let mut size = if number.is_empty() {
+        // a unit on its own stands for one of it
+        1
+    } else {
+        number.parse().map_err(|_| BlockSizeError::TooLarge)?
+    };
+
+    let mut opts = HumanOpts::EXACT;
+
+    // the suffix may not be ASCII, so it's looked at byte by byte
+    if let Some((&letter, rest)) = suffix.as_bytes().split_first() {
+        let (base, rest) = match rest {
+            [b'i', b'B', rest @ ..] => (1024, rest),
+            [b'B' | b'D', rest @ ..] => (1000, rest),
+            _ => (1024, rest),
+        };
+
+        let power = match letter {
+            b'k' | b'K' => 1,
+            b'm' | b'M' => 2,
+            b'g' | b'G' => 3,
+            b't' | b'T' => 4,
+            b'p' | b'P' => 5,
+            b'e' | b'E' => 6,
+            b'z' | b'Z' => 7,
+            b'y' | b'Y' => 8,
+            _ if number.is_empty() => return Err(BlockSizeError::Invalid),
+            _ => return Err(BlockSizeError::InvalidSuffix),
+        };
+
+        for _ in 0..power {
+            size = u64::checked_mul(size, base).ok_or(BlockSizeError::TooLarge)?;
+        }
+
+        if !rest.is_empty() {
+            return Err(BlockSizeError::InvalidSuffix);
+        }
+
+        // a unit without a number is also printed after the sizes
+        if number.is_empty() {
+            opts.suffix = true;
+            opts.bytes = suffix.ends_with('B');
+            opts.base = if !opts.bytes || suffix.ends_with("iB") {
+                1024
+            } else {
+                1000
+            };
+        }
+    }
+
+    if size == 0 {
+        return Err(BlockSizeError::Invalid);
+    }
+
+    Ok((opts, size))
+}
+
+/// Converts `n` units of `from` bytes into units of `to` bytes, rounding up
+/// like GNU's `human_readable`, and appends the result to `out`.
+pub(crate) fn human_readable(n: u64, from: u64, to: u64, opts: HumanOpts, out: &mut Vec<u8>) {
+    // amount, tenths and the rounding state of what is past the tenths
+    // (0: exact, 1: less than half, 2: half, 3: more than half)
+    let (mut amount, mut tenths, mut rounding): (u128, u128, u128);
+    let n = n as u128;
+    let (from, to) = (from as u128, to as u128);
+
+    if opts.autoscale {
+        amount = n * from;
+        tenths = 0;
+        rounding = 0;
+    } else if to <= from && from % to == 0 {
+        amount = n * (from / to);
+        tenths = 0;
+        rounding = 0;
+    } else if from < to && to % from == 0 {
+        let divisor = to / from;
+        let r10 = (n % divisor) * 10;
+        let r2 = (r10 % divisor) * 2;
+        amount = n / divisor;
+        tenths = r10 / divisor;
+        rounding = if r2 < divisor {
+            (r2 != 0) as u128
+        } else {
+            2 + (divisor < r2) as u128
+        };
+    } else {
+        let bytes = n * from;
+        amount = bytes / to;
+        let r10 = (bytes % to) * 10;
+        tenths = r10 / to;
+        let r2 = (r10 % to) * 2;
+        rounding = if r2 < to {
+            (r2 != 0) as u128
+        } else {
+            2 + (to < r2) as u128
+        };
+    }
+
+    let base = opts.base as u128;
+    let mut exponent = 0;
+
+    if opts.autoscale && base <= amount {
+        loop {
+            let r10 = (amount % base) * 10 + tenths;
+            let r2 = (r10 % base) * 2 + (rounding >> 1);
+            amount /= base;
+            tenths = r10 / base;
+            rounding = if r2 < base {
+                (r2 + rounding != 0) as u128
+            } else {
+                2 + (base < r2 + rounding) as u128
+            };
+            exponent += 1;
+
+            if amount < base || exponent == POWER_LETTERS.len() {
+                break;
+            }
+        }
+
+        if amount < 10 {
+            if rounding > 0 {
+                tenths += 1;
+                rounding = 0;
+
+                if tenths == 10 {
+                    amount += 1;
+                    tenths = 0;
+                }
+            }
+
+            if amount < 10 {
+                let mut buf = itoa::Buffer::new();
+                out.extend_from_slice(buf.format(amount).as_bytes());
+                out.push(b'.');
+                out.push(b'0' + tenths as u8);
+                push_suffix(out, exponent, to, opts);
+                return;
+            }
+        }
+    }
+
+    if tenths + rounding > 0 {
+        amount += 1;
+
+        if opts.autoscale && amount == base && exponent < POWER_LETTERS.len() {
+            exponent += 1;
+            out.extend_from_slice(b"1.0");
+            push_suffix(out, exponent, to, opts);
+            return;
+        }
+    }
+
+    let mut buf = itoa::Buffer::new();
+    out.extend_from_slice(buf.format(amount).as_bytes());
+    push_suffix(out, exponent, to, opts);
+}
+
+/// Appends the unit, which is `to` itself unless the value was autoscaled
+fn push_suffix(out: &mut Vec<u8>, mut exponent: usize, to: u128, opts: HumanOpts) {
+    if !opts.suffix {
+        return;
+    }
+
+    if !opts.autoscale {
+        let mut power = 1;
+        while power < to && exponent < POWER_LETTERS.len() {
+            power *= opts.base as u128;
+            exponent += 1;
+        }
+    }
+
+    if exponent != 0 {
+        let letter = POWER_LETTERS[exponent - 1];
+
+        if opts.base == 1000 && letter == b'K' {
+            out.push(b'k');
+        } else {
+            out.push(letter);
+        }
+    }
+
+    if opts.bytes {
+        if opts.base == 1024 && exponent != 0 {
+            out.push(b'i');
+        }
+        out.push(b'B');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_sizes() {
+        assert!(matches!(parse_block_size("1K"), Ok((_, 1024))));
+        assert!(matches!(parse_block_size("2MB"), Ok((_, 2_000_000))));
+        assert!(matches!(parse_block_size("KiB"), Ok((opts, 1024)) if opts.suffix && opts.bytes));
+        assert!(matches!(parse_block_size("human"), Ok((opts, 1)) if opts.autoscale));
+    }
+
+    #[test]
+    fn invalid_block_sizes() {
+        assert!(matches!(parse_block_size(""), Err(BlockSizeError::Invalid)));
+        assert!(matches!(
+            parse_block_size("0"),
+            Err(BlockSizeError::Invalid)
+        ));
+        assert!(matches!(
+            parse_block_size("1X"),
+            Err(BlockSizeError::InvalidSuffix)
+        ));
+        assert!(matches!(
+            parse_block_size("1KQ"),
+            Err(BlockSizeError::InvalidSuffix)
+        ));
+        assert!(matches!(
+            parse_block_size("99999999999999999999"),
+            Err(BlockSizeError::TooLarge)
+        ));
+    }
+
+    #[test]
+    fn non_ascii_suffixes() {
+        assert!(matches!(
+            parse_block_size("é"),
+            Err(BlockSizeError::Invalid)
+        ));
+        assert!(matches!(
+            parse_block_size("1é"),
+            Err(BlockSizeError::InvalidSuffix)
+        ));
+        assert!(matches!(
+            parse_block_size("1Ké"),
+            Err(BlockSizeError::InvalidSuffix)
+        ));
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs,
+    io::{self, Write},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::Path,
+};
+
+const MAX_SYMLINKS: usize = 40;
+
+/// Makes `path` absolute, resolving `.`, `..` and symlinks,
+/// components that don't exist are kept as they are.
+pub(crate) fn canonicalize(path: &Path) -> io::Result<OsString> {
+    let mut pending = Vec::new();
+    if !path.as_os_str().as_bytes().starts_with(b"/") {
+        pending.extend_from_slice(env::current_dir()?.as_os_str().as_bytes());
+        pending.push(b'/');
+    }
+    pending.extend_from_slice(path.as_os_str().as_bytes());
+
+    let mut resolved = Vec::with_capacity(pending.len());
+    let mut links = 0;
+    let mut start = 0;
+
+    while start < pending.len() {
+        let end = pending[start..]
+            .iter()
+            .position(|&b| b == b'/')
+            .map_or(pending.len(), |len| start + len);
+        let component = &pending[start..end];
+        start = end + 1;
+
+        match component {
+            b"" | b"." => continue,
+            b".." => {
+                let parent = resolved.iter().rposition(|&b| b == b'/').unwrap_or(0);
+                resolved.truncate(parent);
+                continue;
+            }
+            _ => {}
+        }
+
+        let len = resolved.len();
+        resolved.push(b'/');
+        resolved.extend_from_slice(component);
+
+        let name = Path::new(OsStr::from_bytes(&resolved));
+        if !fs::symlink_metadata(name).is_ok_and(|meta| meta.file_type().is_symlink()) {
+            continue;
+        }
+
+        links += 1;
+        if links > MAX_SYMLINKS {
+            return Err(io::Error::from_raw_os_error(libc::ELOOP));
+        }
+
+        let target = fs::read_link(name)?.into_os_string().into_vec();
+        if target.starts_with(b"/") {
+            resolved.clear();
+        } else {
+            resolved.truncate(len);
+        }
+
+        let mut rest = target;
+        if start < pending.len() {
+            rest.push(b'/');
+            rest.extend_from_slice(&pending[start..]);
+        }
+        pending = rest;
+        start = 0;
+    }
+
+    if resolved.is_empty() {
+        resolved.push(b'/');
+    }
+
+    Ok(OsString::from_vec(resolved))
+}
+
+/// Writes the OSC 8 sequence that starts a link to `path` on `host`,
+/// percent-encoding the path as RFC 3986 requires (in lowercase, like GNU).
+pub(crate) fn open_link<W: Write>(out: &mut W, host: &[u8], path: &[u8]) -> io::Result<()> {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    let mut url = b"\x1b]8;;file://".to_vec();
+    url.extend_from_slice(host);
+
+    for &byte in path {
+        if byte.is_ascii_alphanumeric() || b"-_.~/".contains(&byte) {
+            url.push(byte);
+        } else {
+            url.extend_from_slice(&[b'%', HEX[(byte >> 4) as usize], HEX[(byte & 0xf) as usize]]);
+        }
+    }
+
+    url.push(0x07);
+    out.write_all(&url)
+}
+
+pub(crate) fn close_link<W: Write>(out: &mut W) -> io::Result<()> {
+    out.write_all(b"\x1b]8;;\x07")
+}
@@ -1,96 +1,637 @@
-#![allow(unused)]
-
+mod colors;
+mod dired;
+mod entry;
+mod human;
+mod hyperlink;
 mod options;
+mod output;
+mod quote;
+mod readdir;
 mod settings;
+mod sort;
+mod stat;
+mod users;
+mod xattr;
 
-use puppyutils::Result;
-use xenia::stdio::stderr;
-use xenia_utils::termios::{Winsize, tcgetwinsize};
-// use rustix::{
-//     fs::{Dir, Mode, OFlags, open},
-//     termios::tcgetwinsize,
-// };
-use std::io::{self, BufWriter, stdout};
+use colors::{Colors, Indicator};
+use dired::{Dired, DiredList, Output};
+use entry::{Entry, Gobble};
+use options::{
+    Dereference, Formatting, IndicatorStyle, Personality, QuotingStyle, SortOrder, TimeField,
+};
+use puppyutils::{Result, error_message, time::TimeZone, utf8};
+use quote::Quoting;
+use readdir::ReadDir;
+use settings::{LsConfig, LsFlags};
+use std::{
+    collections::HashSet,
+    env,
+    ffi::{OsStr, OsString},
+    fs,
+    io::{self, BufWriter, Write, stdout},
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::Path,
+    process::exit,
+};
+use xenia::{ClockId, Timespec, clock_gettime, uname};
+use xenia_utils::termios::{isatty, tcgetwinsize};
 
 const CURRENT_DIR_PATH: &str = ".";
 
+const LS_MINOR_PROBLEM: i32 = 1;
+const LS_FAILURE: i32 = 2;
+
+/// Matches a name against an `-I` or `--hide` pattern, where like with
+/// `FNM_PERIOD` a leading `.` has to be matched by a `.` in the pattern
+fn matches_name(pattern: &[u8], name: &[u8]) -> bool {
+    (!name.starts_with(b".") || pattern.starts_with(b".")) && colors::fnmatch(pattern, name)
+}
+
+/// A directory waiting to be listed, `None` marks the point
+/// where a directory's subtree is done during recursion.
+struct PendingDir {
+    name: Option<OsString>,
+    command_line: bool,
+}
+
+struct Ls<O: Write> {
+    cfg: LsConfig,
+    out: Output<O>,
+    exit_status: i32,
+
+    pending: Vec<PendingDir>,
+    /// device and inode of the directories being listed, used to detect loops with `-R`
+    active_dirs: HashSet<(u64, u64)>,
+    active_stack: Vec<(u64, u64)>,
+
+    print_dir_name: bool,
+    first: bool,
+
+    now: Timespec,
+    tz: TimeZone,
+
+    /// `None` when the output is not colored
+    colors: Option<Colors>,
+    /// host name used in `--hyperlink` URLs, `None` without it
+    hyperlink_host: Option<Vec<u8>>,
+    /// positions to report with `--dired`, `None` without it
+    dired: Option<Dired>,
+
+    filename_quoting: Quoting,
+    dirname_quoting: Quoting,
+    /// whether names without quotes are moved a column right,
+    /// to line up with the quoted ones
+    align_quotes: bool,
+    /// whether any name in the listing being printed is quoted
+    some_quoted: bool,
+
+    names: users::Names,
+}
+
 pub fn main() -> Result {
-    let mut stdout = stdout();
-    let winsize = get_win_size();
-    let _cfg = settings::parse_arguments(winsize.ws_col, &mut stdout)?;
+    run(Personality::Ls)
+}
 
-    // FIXME: re-implement using xenia
+/// `dir`, listing in columns with escapes wherever the output goes
+#[allow(dead_code)] // only the multi-call binary has it
+pub fn dir() -> Result {
+    run(Personality::Dir)
+}
 
-    // let fd = open(
-    //     CURRENT_DIR_PATH,
-    //     OFlags::DIRECTORY | OFlags::RDONLY,
-    //     Mode::RUSR,
-    // )?;
+/// `vdir`, listing in long format with escapes
+#[allow(dead_code)]
+pub fn vdir() -> Result {
+    run(Personality::Vdir)
+}
 
-    // let dir = Dir::new(fd)?;
+fn run(personality: Personality) -> Result {
+    let mut stdout = stdout();
+    let tty = isatty(xenia::stdio::stdout());
+    let cfg = settings::parse_arguments(personality, line_width(), tty, &mut stdout)?;
+    let program = personality.name();
+    let colors = cfg
+        .color
+        .enabled(tty)
+        .then(|| Colors::from_env(program))
+        .flatten();
+    let hyperlink_host = cfg
+        .hyperlink_file_names
+        .enabled(tty)
+        .then(|| uname().nodename().to_bytes().to_vec());
+    let dired = (cfg.flags.contains(LsFlags::EMACS_DIRED_MODE)
+        && cfg.format == Formatting::Long
+        && hyperlink_host.is_none())
+    .then(Dired::default);
 
-    // // bad bad bad
-    // // FIXME: do not allocate
-    // let names = dir
-    //     .filter_map(Result::ok)
-    //     .map(|entry| entry.file_name().to_string_lossy().into_owned())
-    //     .filter(|entry| !entry.starts_with('.'))
-    //     .collect::<Vec<_>>();
+    if dired.is_some() && cfg.flags.contains(LsFlags::END_WITH_NUL) {
+        eprintln!("{program}: --dired and --zero are incompatible");
+        exit(LS_FAILURE);
+    }
 
-    let names = Vec::new();
+    let style = cfg.quoting;
+    let utf8 = utf8::locale_is_utf8();
+    let mut filename_quoting = Quoting::new(style, utf8);
+    if style == QuotingStyle::Escape {
+        filename_quoting = filename_quoting.with_char(b' ');
+    }
+    let indicator_chars: &[u8] = match cfg.indicator {
+        IndicatorStyle::FileType => b"*=>@|",
+        IndicatorStyle::Classify => b"=>@|",
+        _ => b"",
+    };
+    for &c in indicator_chars {
+        filename_quoting = filename_quoting.with_char(c);
+    }
 
-    let mut stdout = BufWriter::new(stdout);
+    let align_quotes = (cfg.format == Formatting::Long
+        || (matches!(
+            cfg.format,
+            Formatting::Vertical | Formatting::Across | Formatting::Horizontal
+        ) && cfg.width != 0))
+        && matches!(
+            style,
+            QuotingStyle::Shell | QuotingStyle::ShellEscape | QuotingStyle::CMaybe
+        );
 
-    print_all(names, &mut stdout)?;
+    let mut ls = Ls {
+        cfg,
+        out: Output::new(BufWriter::new(stdout)),
+        exit_status: 0,
+        pending: Vec::new(),
+        active_dirs: HashSet::new(),
+        active_stack: Vec::new(),
+        print_dir_name: true,
+        first: true,
+        now: clock_gettime(ClockId::Realtime)?,
+        tz: TimeZone::local(),
+        colors,
+        hyperlink_host,
+        dired,
+        filename_quoting,
+        dirname_quoting: Quoting::new(style, utf8).with_char(b':'),
+        align_quotes,
+        some_quoted: false,
+        names: users::Names::default(),
+    };
+
+    ls.run()?;
+    if let Some(dired) = &ls.dired {
+        dired.dump(&mut ls.out, ls.cfg.quoting.name())?;
+    }
+    if let Some(colors) = &mut ls.colors {
+        colors.finish(&mut ls.out.inner)?;
+    }
+    ls.out.flush()?;
+
+    if ls.exit_status != 0 {
+        exit(ls.exit_status);
+    }
 
     Ok(())
 }
 
-fn get_win_size() -> Winsize {
-    let stderr_fd = stderr();
-    tcgetwinsize(stderr_fd).expect("couldn't get terminal size")
+fn line_width() -> u16 {
+    if let Some(width) = env::var("COLUMNS")
+        .ok()
+        .and_then(|cols| cols.parse::<u16>().ok())
+        .filter(|&cols| cols > 0)
+    {
+        return width;
+    }
+
+    match tcgetwinsize(xenia::stdio::stdout()) {
+        Ok(winsize) if winsize.ws_col > 0 => winsize.ws_col,
+        _ => 80,
+    }
 }
 
-// FIXME: This algorithm to print out lines is incredibly simplistic
-// and slightly worse than the one used in GNU's ls.
-fn print_all<O: io::Write>(cols: Vec<String>, stdout: &mut O) -> Result {
-    const MIN_COLUMN_WIDTH: u16 = 3;
+impl<O: Write> Ls<O> {
+    fn run(&mut self) -> io::Result<()> {
+        let files = std::mem::take(&mut self.cfg.files);
+        let list_dirs = self.cfg.flags.contains(LsFlags::LIST_DIRECTORIES);
+        let mut entries = Vec::new();
+
+        if files.is_empty() {
+            if list_dirs {
+                self.gobble_arg(OsStr::new(CURRENT_DIR_PATH), &mut entries)?;
+            } else {
+                self.pending.push(PendingDir {
+                    name: Some(CURRENT_DIR_PATH.into()),
+                    command_line: true,
+                });
+            }
+        } else {
+            for file in &files {
+                self.gobble_arg(OsStr::new(file), &mut entries)?;
+            }
+        }
 
-    let len = cols.len();
-    let stderr_fd = stderr();
-    let winsize = tcgetwinsize(stderr_fd).expect("couldn't get terminal size");
+        let mut extracted = Vec::new();
 
-    let max_idx = ((winsize.ws_col / 3) / MIN_COLUMN_WIDTH - 1) as usize;
+        if !entries.is_empty() {
+            sort::sort_entries(&mut entries, &self.cfg, |entry| self.quoted_width(entry));
 
-    let max_cols = if max_idx < len { max_idx } else { len };
+            if !list_dirs {
+                extracted = self.extract_dirs(&mut entries, None);
+            }
+        }
 
-    print_into_columns(cols.iter().map(String::as_str), max_cols, stdout)
-}
+        if !entries.is_empty() {
+            self.print_entries(&entries, &extracted)?;
 
-fn print_into_columns<I, O: io::Write>(iter: I, columns: usize, stdout: &mut O) -> Result
-where
-    I: IntoIterator<Item: AsRef<str> + core::fmt::Display>,
-{
-    let mut counter = 0;
-    for line in iter {
-        if counter == columns {
-            stdout.write_all(b"\n")?;
-            counter = 0;
+            if !self.pending.is_empty() {
+                self.out.write_all(b"\n")?;
+            }
+        } else if files.len() <= 1 && self.pending.len() == 1 {
+            self.print_dir_name = false;
         }
 
-        if counter == columns - 1 {
-            stdout.write_all(line.as_ref().as_bytes())?;
-        } else {
-            stdout.write_all(line.as_ref().as_bytes())?;
-            stdout.write_all(b"  ")?;
+        while let Some(dir) = self.pending.pop() {
+            match dir.name {
+                Some(name) => self.print_dir(&name, dir.command_line)?,
+                None => {
+                    if let Some(dev_ino) = self.active_stack.pop() {
+                        self.active_dirs.remove(&dev_ino);
+                    }
+                }
+            }
+
+            self.print_dir_name = true;
         }
 
-        counter += 1;
+        Ok(())
     }
 
-    // fixes the shell returning a "return symbol" at the end.
-    stdout.write_all(b"\n")?;
-    stdout.flush()?;
+    fn gobble_opts(&self) -> Gobble {
+        let long = self.cfg.format == Formatting::Long;
+        // symlinks to directories are grouped with them
+        let check_links = self.cfg.flags.contains(LsFlags::GROUP_DIRS_FIRST)
+            || self
+                .colors
+                .as_ref()
+                .is_some_and(|colors| colors.needs_link_targets(long));
 
-    Ok(())
+        let indicate_links = matches!(
+            self.cfg.indicator,
+            IndicatorStyle::FileType | IndicatorStyle::Classify
+        );
+
+        let flags = &self.cfg.flags;
+        let print_inode = flags.contains(LsFlags::PRINT_INODE_INDEXES);
+        let print_blocks = flags.contains(LsFlags::PRINT_ALLOCATED_SIZE);
+        let print_context = flags.contains(LsFlags::PRINT_SECURIT_CTXT);
+        let sort_by_meta = matches!(
+            self.cfg.order,
+            SortOrder::Time | SortOrder::AccessTime | SortOrder::Size
+        );
+
+        // like GNU, files in directories are only `stat`ed when something
+        // shown needs more than their name, type and inode number
+        let needs_stat =
+            sort_by_meta || long || print_blocks || print_context || self.hyperlink_host.is_some();
+        let needs_type = !needs_stat
+            && (flags.contains(LsFlags::RECURSIVE)
+                || self.colors.is_some()
+                || flags.contains(LsFlags::GROUP_DIRS_FIRST)
+                || self.cfg.indicator != IndicatorStyle::None);
+
+        let colored = |indicators: &[Indicator]| {
+            self.colors
+                .as_ref()
+                .is_some_and(|colors| indicators.iter().any(|&ind| colors.is_colored(ind)))
+        };
+        let follow_links = self.cfg.deref == Dereference::Always
+            || check_links
+            || self
+                .colors
+                .as_ref()
+                .is_some_and(Colors::symlink_as_referent);
+        let stat_links = (print_inode || needs_type) && follow_links;
+
+        Gobble {
+            deref: self.cfg.deref,
+            mask: self.stat_mask(),
+            stat_all: needs_stat,
+            stat_unknown: needs_type || stat_links,
+            stat_dirs: colored(&[
+                Indicator::OtherWritable,
+                Indicator::Sticky,
+                Indicator::StickyOtherWritable,
+            ]),
+            stat_files: needs_type
+                && (self.cfg.indicator == IndicatorStyle::Classify
+                    || colored(&[Indicator::Exec, Indicator::SetUid, Indicator::SetGid])),
+            stat_links,
+            read_link: long || check_links,
+            stat_link: indicate_links || check_links,
+            context: long || self.cfg.flags.contains(LsFlags::PRINT_SECURIT_CTXT),
+            acl: long,
+            capability: self
+                .colors
+                .as_ref()
+                .is_some_and(|colors| colors.is_colored(Indicator::Cap)),
+        }
+    }
+
+    /// The fields asked of `statx`, only those that are shown or sorted by
+    fn stat_mask(&self) -> u32 {
+        let flags = &self.cfg.flags;
+        let time = match self.cfg.time {
+            TimeField::Modification => stat::STATX_MTIME,
+            TimeField::Access => stat::STATX_ATIME,
+            TimeField::Change => stat::STATX_CTIME,
+            TimeField::Birth => stat::STATX_BTIME,
+        };
+
+        let mut mask = stat::STATX_TYPE | stat::STATX_MODE;
+
+        if flags.contains(LsFlags::PRINT_INODE_INDEXES) {
+            mask |= stat::STATX_INO;
+        }
+
+        if flags.contains(LsFlags::PRINT_ALLOCATED_SIZE) {
+            mask |= stat::STATX_BLOCKS;
+        }
+
+        if self.cfg.format == Formatting::Long {
+            mask |= stat::STATX_NLINK | stat::STATX_SIZE | time;
+
+            if !flags.contains(LsFlags::NO_OWNER_LISTED) || flags.contains(LsFlags::PRINT_AUTHOR) {
+                mask |= stat::STATX_UID;
+            }
+
+            if !flags.contains(LsFlags::NO_GROUPS_LISTED) {
+                mask |= stat::STATX_GID;
+            }
+        }
+
+        match self.cfg.order {
+            SortOrder::Time | SortOrder::AccessTime => mask |= time,
+            SortOrder::Size => mask |= stat::STATX_SIZE,
+            _ => {}
+        }
+
+        mask
+    }
+
+    fn gobble_arg(&mut self, name: &OsStr, entries: &mut Vec<Entry>) -> io::Result<()> {
+        let (mut entry, err) = Entry::gobble(
+            name.to_owned(),
+            Path::new(name),
+            0,
+            0,
+            self.gobble_opts(),
+            true,
+        );
+        entry.absolute_name = self.absolute_name(Path::new(name), true)?;
+
+        match err {
+            Some(err) => self.file_failure(true, "cannot access", name, &err)?,
+            None => entries.push(entry),
+        }
+
+        Ok(())
+    }
+
+    /// Moves directories out of `entries` onto the pending stack,
+    /// `dir` is the directory they were read from, `None` for arguments.
+    /// Returns the arguments that are no longer listed as files.
+    fn extract_dirs(&mut self, entries: &mut Vec<Entry>, dir: Option<&OsStr>) -> Vec<Entry> {
+        if dir.is_some() && self.cfg.flags.contains(LsFlags::RECURSIVE) {
+            self.pending.push(PendingDir {
+                name: None,
+                command_line: false,
+            });
+        }
+
+        for entry in entries.iter().rev() {
+            if entry.is_dir() && (dir.is_none() || !entry.is_dot_or_dotdot()) {
+                let name = match dir {
+                    Some(dir) if !entry.name.as_bytes().starts_with(b"/") => {
+                        entry::concat(dir, &entry.name)
+                    }
+                    _ => entry.name.clone(),
+                };
+
+                self.pending.push(PendingDir {
+                    name: Some(name),
+                    command_line: entry.command_line,
+                });
+            }
+        }
+
+        entries
+            .extract_if(.., |entry| entry.command_line && entry.is_dir())
+            .collect()
+    }
+
+    fn print_dir(&mut self, name: &OsStr, command_line: bool) -> io::Result<()> {
+        let path = Path::new(name);
+
+        let read_dir = match ReadDir::open(path) {
+            Ok(read_dir) => read_dir,
+            Err(err) => {
+                return self.file_failure(command_line, "cannot open directory", name, &err);
+            }
+        };
+
+        if self.cfg.flags.contains(LsFlags::RECURSIVE) {
+            let meta = match fs::metadata(path) {
+                Ok(meta) => meta,
+                Err(err) => {
+                    return self.file_failure(
+                        command_line,
+                        "cannot determine device and inode of",
+                        name,
+                        &err,
+                    );
+                }
+            };
+
+            let dev_ino = (meta.dev(), meta.ino());
+
+            if !self.active_dirs.insert(dev_ino) {
+                self.out.flush()?;
+                let mut stderr = io::stderr();
+                write!(stderr, "{}: ", self.cfg.personality.name())?;
+                stderr.write_all(&self.quote_error(name, QuotingStyle::ShellEscape, true))?;
+                stderr.write_all(b": not listing already-listed directory\n")?;
+                self.exit_status = LS_FAILURE;
+
+                return Ok(());
+            }
+
+            self.active_stack.push(dev_ino);
+        }
+
+        if self.cfg.flags.contains(LsFlags::RECURSIVE) || self.print_dir_name {
+            if !self.first {
+                self.out.write_all(b"\n")?;
+            }
+            self.first = false;
+
+            self.dired_indent()?;
+
+            let absolute_name = self.absolute_name(path, command_line)?;
+            // names in the header are never padded
+            self.some_quoted = false;
+            let quoted = self.quote(name.as_bytes(), self.dirname_quoting);
+            self.quote_name(
+                &quoted,
+                None,
+                absolute_name.as_deref(),
+                Some(DiredList::Subdirs),
+            )?;
+
+            self.out.write_all(b":\n")?;
+        }
+
+        let opts = self.gobble_opts();
+        let mut entries = Vec::new();
+
+        // with nothing to sort or line up, files are printed as they are read,
+        // so huge directories don't have to be kept in memory
+        let streaming = self.cfg.format == Formatting::SingleCol
+            && self.cfg.order == SortOrder::None
+            && !self
+                .cfg
+                .flags
+                .intersects(LsFlags::PRINT_ALLOCATED_SIZE | LsFlags::RECURSIVE);
+
+        for dirent in read_dir {
+            let dirent = match dirent {
+                Ok(dirent) => dirent,
+                Err(err) => {
+                    self.file_failure(command_line, "reading directory", name, &err)?;
+                    break;
+                }
+            };
+
+            if self.is_ignored(dirent.name.as_bytes()) {
+                continue;
+            }
+
+            let path = entry::attach(name, &dirent.name);
+            let (mut entry, err) = Entry::gobble(
+                dirent.name,
+                &path,
+                dirent.file_type,
+                dirent.ino,
+                opts,
+                false,
+            );
+            entry.absolute_name = self.absolute_name(&path, false)?;
+
+            if let Some(err) = err {
+                self.file_failure(false, "cannot access", path.as_os_str(), &err)?;
+            }
+
+            entries.push(entry);
+
+            if streaming {
+                self.print_entries(&entries, &[])?;
+                entries.clear();
+            }
+        }
+
+        sort::sort_entries(&mut entries, &self.cfg, |entry| self.quoted_width(entry));
+
+        if self.cfg.flags.contains(LsFlags::RECURSIVE) {
+            self.extract_dirs(&mut entries, Some(name));
+        }
+
+        if self.cfg.format == Formatting::Long
+            || self.cfg.flags.contains(LsFlags::PRINT_ALLOCATED_SIZE)
+        {
+            self.print_total(&entries)?;
+        }
+
+        if !entries.is_empty() {
+            self.print_entries(&entries, &[])?;
+        }
+
+        Ok(())
+    }
+
+    /// Canonical name of a file to link to, only resolved with `--hyperlink`
+    fn absolute_name(&mut self, path: &Path, command_line: bool) -> io::Result<Option<OsString>> {
+        if self.hyperlink_host.is_none() {
+            return Ok(None);
+        }
+
+        match hyperlink::canonicalize(path) {
+            Ok(absolute_name) => Ok(Some(absolute_name)),
+            Err(err) => {
+                self.file_failure(command_line, "error canonicalizing", path.as_os_str(), &err)?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn is_ignored(&self, name: &[u8]) -> bool {
+        let flags = &self.cfg.flags;
+        let show_dots =
+            flags.intersects(LsFlags::NOT_IGNORE_DOTS | LsFlags::IGNORE_DOTS_EXCEPT_DIRS);
+
+        if name.starts_with(b".") && !flags.contains(LsFlags::NOT_IGNORE_DOTS) {
+            let dot_or_dotdot = matches!(name, b"." | b"..");
+
+            if !flags.contains(LsFlags::IGNORE_DOTS_EXCEPT_DIRS) || dot_or_dotdot {
+                return true;
+            }
+        }
+
+        // --hide gives way to -a and -A, -I doesn't
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|pattern| matches_name(pattern.as_bytes(), name))
+        };
+
+        (flags.contains(LsFlags::IGNORE_TILDE_ENTRIES) && name.ends_with(b"~"))
+            || (!show_dots && matches(&self.cfg.hide))
+            || matches(&self.cfg.ignore)
+    }
+
+    /// Reports a file that could not be listed, `serious` problems
+    /// (those with command line arguments) exit with status 2.
+    fn file_failure(
+        &mut self,
+        serious: bool,
+        message: &str,
+        name: &OsStr,
+        err: &io::Error,
+    ) -> io::Result<()> {
+        self.out.flush()?;
+
+        let mut stderr = io::stderr();
+        write!(stderr, "{}: ", self.cfg.personality.name())?;
+        stderr.write_all(message.as_bytes())?;
+        stderr.write_all(b" ")?;
+        stderr.write_all(&self.quote_error(name, QuotingStyle::ShellEscapeAlways, false))?;
+        stderr.write_all(b": ")?;
+        stderr.write_all(error_message(err).as_bytes())?;
+        stderr.write_all(b"\n")?;
+
+        if serious {
+            self.exit_status = LS_FAILURE;
+        } else if self.exit_status == 0 {
+            self.exit_status = LS_MINOR_PROBLEM;
+        }
+
+        Ok(())
+    }
+
+    /// A name quoted for a diagnostic, `colon` also quotes `:`
+    /// for when one follows the name.
+    fn quote_error(&self, name: &OsStr, style: QuotingStyle, colon: bool) -> Vec<u8> {
+        let mut quoting = Quoting::new(style, self.filename_quoting.utf8);
+        if colon {
+            quoting = quoting.with_char(b':');
+        }
+
+        let mut buf = Vec::new();
+        quoting.quote(name.as_bytes(), &mut buf);
+        buf
+    }
 }
@@ -1,4 +1,45 @@
 #![allow(dead_code, unused_variables)]
+use puppyutils::{help_text, version_text};
+
+/// The names `ls` goes by, which change its defaults
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub(crate) enum Personality {
+    Ls,
+    /// like `ls -C -b`
+    Dir,
+    /// like `ls -l -b`
+    Vdir,
+}
+
+impl Personality {
+    /// The name used in diagnostics
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Personality::Ls => "ls",
+            Personality::Dir => "dir",
+            Personality::Vdir => "vdir",
+        }
+    }
+
+    pub(crate) fn help_text(self) -> &'static str {
+        match self {
+            Personality::Ls => help_text!("ls"),
+            Personality::Dir => help_text!("dir"),
+            Personality::Vdir => help_text!("vdir"),
+        }
+    }
+
+    pub(crate) fn version_text(self) -> &'static str {
+        match self {
+            Personality::Ls => version_text!("ls"),
+            Personality::Dir => version_text!("dir"),
+            Personality::Vdir => version_text!("vdir"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub(crate) enum SortOrder {
     None,
@@ -13,18 +54,74 @@ pub(crate) enum SortOrder {
 }
 
 #[non_exhaustive]
-#[repr(u8)]
+#[derive(Clone, PartialEq, Eq)]
 pub(crate) enum TimeStampType {
     FullIso,
     LongIso,
     Iso,
     Locale,
-    // Format
+    /// `+FORMAT`, with an optional second line for recent files
+    Format(String),
+}
+
+impl TimeStampType {
+    /// Parses a `--time-style` argument, `None` if it is invalid
+    pub(crate) fn from_style(style: &str) -> Option<Self> {
+        if let Some(format) = style.strip_prefix('+') {
+            if format.matches('\n').count() > 1 {
+                return None;
+            }
+
+            return Some(TimeStampType::Format(format.to_owned()));
+        }
+
+        // `posix-` styles only differ from the locale style outside
+        // of the POSIX locale, which is the only one we know of.
+        if style.starts_with("posix-") {
+            return Some(TimeStampType::Locale);
+        }
+
+        match style {
+            "full-iso" => Some(TimeStampType::FullIso),
+            "long-iso" => Some(TimeStampType::LongIso),
+            "iso" => Some(TimeStampType::Iso),
+            "locale" => Some(TimeStampType::Locale),
+            _ => None,
+        }
+    }
+
+    /// The `strftime` formats used for old and recent files
+    pub(crate) fn formats(&self) -> [&[u8]; 2] {
+        match self {
+            TimeStampType::FullIso => [b"%Y-%m-%d %H:%M:%S.%N %z"; 2],
+            TimeStampType::LongIso => [b"%Y-%m-%d %H:%M"; 2],
+            TimeStampType::Iso => [b"%Y-%m-%d ", b"%m-%d %H:%M"],
+            TimeStampType::Locale => [b"%b %e  %Y", b"%b %e %H:%M"],
+            TimeStampType::Format(format) => match format.split_once('\n') {
+                Some((old, recent)) => [old.as_bytes(), recent.as_bytes()],
+                None => [format.as_bytes(); 2],
+            },
+        }
+    }
+}
+
+/// Which of the file's timestamps is shown and sorted by
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub(crate) enum TimeField {
+    Modification,
+    Access,
+    Change,
+    Birth,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub(crate) enum QuotingStyle {
     C,
+    CMaybe,
+    Clocale,
+    Escape,
     Literal,
     Locale,
     Shell,
@@ -33,6 +130,25 @@ pub(crate) enum QuotingStyle {
     ShellEscapeAlways,
 }
 
+impl QuotingStyle {
+    /// The name of the style, as given to `--quoting-style`
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            QuotingStyle::C => "c",
+            QuotingStyle::CMaybe => "c-maybe",
+            QuotingStyle::Clocale => "clocale",
+            QuotingStyle::Escape => "escape",
+            QuotingStyle::Literal => "literal",
+            QuotingStyle::Locale => "locale",
+            QuotingStyle::Shell => "shell",
+            QuotingStyle::ShellAlways => "shell-always",
+            QuotingStyle::ShellEscape => "shell-escape",
+            QuotingStyle::ShellEscapeAlways => "shell-escape-always",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub(crate) enum IndicatorStyle {
     None,
@@ -41,15 +157,18 @@ pub(crate) enum IndicatorStyle {
     Classify,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub(crate) enum Formatting {
     Long,
     Horizontal,
+    Vertical,
     Across,
     Commas,
     SingleCol,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub(crate) enum When {
     Never,
@@ -57,37 +176,53 @@ pub(crate) enum When {
     Always,
 }
 
+impl When {
+    /// Whether the option is on, `tty` tells if standard output is a terminal
+    #[inline]
+    pub(crate) fn enabled(self, tty: bool) -> bool {
+        match self {
+            When::Never => false,
+            When::Auto => tty,
+            When::Always => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Dereference {
+    // just don't do it.
+    Never,
+
     // follow symlinks listed on the command line
-    Commandline,
+    CommandLineArguments,
 
-    // follow all symlinks in the directory
+    // follow symlinks listed on the command line
     // only if they point to a directory
-    FollowAllDirs,
+    CommandLineSymlinkToDir,
 
-    // just don't do it.
-    None,
+    // follow all symlinks
+    Always,
 }
 
 macro_rules! from_bytes {
-    ($($item: ident >> $({$($ret: tt)*} => {$($tree: tt)*})*),*) => {
+    ($($item: ident >> $({$ret: ident} => {$($name: literal)|*})*),*) => {
         $(
             impl $item {
+                /// The names of each value for `argmatch`, synonyms next to each other
+                pub(crate) const NAMES: &[(&str, $item)] = &[$($(($name, $item::$ret),)*)*];
+
                 /// Generates an enum from the provided bytes,
-                /// returns a error-handling closure if they
-                /// can't be converted to an appropriate type.
+                /// `None` if they don't name any value.
                 #[inline]
                 pub(crate) fn from_bytes<A>(val: A) -> Option<$item>
                 where
                     A: AsRef<[u8]>,
                 {
-                    match val.as_ref() {
-                         $($($tree)* => Some($item::$($ret)*),)*
-
-                         _ => None
-
-                    }
+                    Self::NAMES
+                        .iter()
+                        .find(|(name, _)| name.as_bytes() == val.as_ref())
+                        .map(|&(_, item)| item)
                 }
             }
         )*
@@ -95,31 +230,40 @@ macro_rules! from_bytes {
 }
 
 from_bytes! {
-    When >> {Always} => {b"always" | b"yes" | b"force"}
-          {Never} => {b"never"|b"no"|b"none"}
-          {Auto} => {b"auto" | b"tty" | b"if-tty"},
-
-    Formatting >> {Long} => {b"verbose" | b"long"}
-                  {Horizontal} => {b"horizontal"}
-                  {Across} => {b"across"}
-                  {Commas} => {b"commas"}
-                  {SingleCol} => {b"single-column"},
-
-    IndicatorStyle >> {None} => {b"none"}
-                      {Slash} => {b"slash"}
-                      {FileType} => {b"file-type"}
-                      {Classify} => {b"classify"},
-
-    QuotingStyle >> {Literal} => {b"literal"}
-                    {Locale} => {b"locale"}
-                    {Shell} => {b"shell"}
-                    {ShellEscape} => {b"shell-always"}
-                    {ShellEscapeAlways} => {b"shell-escape-always"}
-                    {C} => {b"c"},
-    SortOrder >> {None} => {b"none"}
-                 {Size} => {b"size"}
-                 {Time} => {b"time"}
-                 {Version} => {b"version"}
-                 {Extension} => {b"extension"}
-                 {Width} => {b"width"}
+    When >> {Always} => {"always" | "yes" | "force"}
+          {Never} => {"never" | "no" | "none"}
+          {Auto} => {"auto" | "tty" | "if-tty"},
+
+    TimeField >> {Access} => {"atime" | "access" | "use"}
+                 {Change} => {"ctime" | "status"}
+                 {Modification} => {"mtime" | "modification"}
+                 {Birth} => {"birth" | "creation"},
+
+    Formatting >> {Long} => {"verbose" | "long"}
+                  {Commas} => {"commas"}
+                  {Horizontal} => {"horizontal" | "across"}
+                  {Vertical} => {"vertical"}
+                  {SingleCol} => {"single-column"},
+
+    IndicatorStyle >> {None} => {"none"}
+                      {Slash} => {"slash"}
+                      {FileType} => {"file-type"}
+                      {Classify} => {"classify"},
+
+    QuotingStyle >> {Literal} => {"literal"}
+                    {Shell} => {"shell"}
+                    {ShellAlways} => {"shell-always"}
+                    {ShellEscape} => {"shell-escape"}
+                    {ShellEscapeAlways} => {"shell-escape-always"}
+                    {C} => {"c"}
+                    {CMaybe} => {"c-maybe"}
+                    {Escape} => {"escape"}
+                    {Locale} => {"locale"}
+                    {Clocale} => {"clocale"},
+    SortOrder >> {None} => {"none"}
+                 {Time} => {"time"}
+                 {Size} => {"size"}
+                 {Extension} => {"extension"}
+                 {Version} => {"version"}
+                 {Width} => {"width"}
 }
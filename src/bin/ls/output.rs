@@ -0,0 +1,893 @@
+use std::{
+    ffi::OsStr,
+    io::{self, Write},
+    os::unix::ffi::OsStrExt,
+};
+
+use xenia::{ClockId, clock_gettime};
+
+use super::{
+    Ls,
+    colors::{Color, Indicator},
+    dired::DiredList,
+    entry::{Entry, S_IFBLK, S_IFCHR, S_IFDIR, S_IFIFO, S_IFLNK, S_IFMT, S_IFREG, S_IFSOCK},
+    human::human_readable,
+    hyperlink,
+    options::{Formatting, IndicatorStyle, QuotingStyle},
+    quote::Quoting,
+    settings::LsFlags,
+};
+
+const MIN_COLUMN_WIDTH: usize = 3;
+
+/// Half of an average Gregorian year, files older than this
+/// (or in the future) are shown with their year instead of their time.
+const SIX_MONTHS: i64 = 31556952 / 2;
+
+impl<O: Write> Ls<O> {
+    /// Prints `entries`, `extracted` are the directories moved out of them to be
+    /// listed on their own, which columns are still made wide enough for.
+    pub(super) fn print_entries(
+        &mut self,
+        entries: &[Entry],
+        extracted: &[Entry],
+    ) -> io::Result<()> {
+        let frills = self.frill_widths(entries.iter().chain(extracted));
+        self.some_quoted = self.align_quotes
+            && entries
+                .iter()
+                .chain(extracted)
+                .any(|entry| self.needs_quoting(entry.name.as_bytes()));
+
+        match self.cfg.format {
+            Formatting::Long => self.print_long(entries, extracted, frills),
+            Formatting::Vertical => self.print_columns(entries, frills, true),
+            Formatting::Across | Formatting::Horizontal => {
+                self.print_columns(entries, frills, false)
+            }
+            Formatting::Commas => self.print_with_commas(entries),
+            Formatting::SingleCol => self.print_single_column(entries, frills),
+        }
+    }
+
+    /// The byte ending lines of entries, NUL with `--zero`
+    fn eol(&self) -> u8 {
+        if self.cfg.flags.contains(LsFlags::END_WITH_NUL) {
+            b'\0'
+        } else {
+            b'\n'
+        }
+    }
+
+    /// Widths of the `-i`, `-s` and `-Z` columns
+    fn frill_widths<'a>(&self, entries: impl Iterator<Item = &'a Entry>) -> Frills {
+        entries
+            .map(|entry| self.entry_frills(entry))
+            .fold(Frills::default(), |widths, frills| Frills {
+                inode: widths.inode.max(frills.inode),
+                blocks: widths.blocks.max(frills.blocks),
+                context: widths.context.max(frills.context),
+            })
+    }
+
+    /// Widths of the `-i`, `-s` and `-Z` columns for `entry` alone
+    fn entry_frills(&self, entry: &Entry) -> Frills {
+        let mut frills = Frills::default();
+        let mut buf = Vec::new();
+
+        if self.cfg.flags.contains(LsFlags::PRINT_INODE_INDEXES) {
+            push_inode(entry, &mut buf);
+            frills.inode = buf.len();
+        }
+
+        if self.cfg.flags.contains(LsFlags::PRINT_ALLOCATED_SIZE) {
+            buf.clear();
+            self.push_blocks(entry, &mut buf);
+            frills.blocks = buf.len();
+        }
+
+        if self.cfg.flags.contains(LsFlags::PRINT_SECURIT_CTXT) {
+            frills.context = context(entry).len();
+        }
+
+        frills
+    }
+
+    /// Appends the inode number and block count that go before a name, if asked for
+    fn push_frills(&self, entry: &Entry, frills: Frills, line: &mut Vec<u8>) {
+        let mut buf = Vec::new();
+
+        if self.cfg.flags.contains(LsFlags::PRINT_INODE_INDEXES) {
+            push_inode(entry, &mut buf);
+            pad_left(line, &buf, frills.inode);
+            line.push(b' ');
+        }
+
+        if self.cfg.flags.contains(LsFlags::PRINT_ALLOCATED_SIZE) {
+            buf.clear();
+            self.push_blocks(entry, &mut buf);
+            pad_left(line, &buf, frills.blocks);
+            line.push(b' ');
+        }
+    }
+
+    /// Width taken by what `push_frills` appends, and the context with `-Z`
+    fn frills_width(&self, frills: Frills) -> usize {
+        let mut width = 0;
+
+        if self.cfg.flags.contains(LsFlags::PRINT_INODE_INDEXES) {
+            width += frills.inode + 1;
+        }
+
+        if self.cfg.flags.contains(LsFlags::PRINT_ALLOCATED_SIZE) {
+            width += frills.blocks + 1;
+        }
+
+        if self.cfg.flags.contains(LsFlags::PRINT_SECURIT_CTXT) {
+            width += frills.context + 1;
+        }
+
+        width
+    }
+
+    fn push_blocks(&self, entry: &Entry, out: &mut Vec<u8>) {
+        let (opts, block_size) = self.cfg.block_size;
+
+        match &entry.meta {
+            Some(meta) => human_readable(meta.blocks, 512, block_size, opts, out),
+            None => out.push(b'?'),
+        }
+    }
+
+    pub(super) fn print_total(&mut self, entries: &[Entry]) -> io::Result<()> {
+        let total = entries.iter().map(Entry::blocks).sum();
+        let (opts, block_size) = self.cfg.block_size;
+
+        let mut buf = b"total ".to_vec();
+        human_readable(total, 512, block_size, opts, &mut buf);
+        buf.push(self.eol());
+
+        self.dired_indent()?;
+        self.out.write_all(&buf)
+    }
+
+    /// Long listings are indented with `--dired`
+    pub(super) fn dired_indent(&mut self) -> io::Result<()> {
+        if self.dired.is_some() {
+            self.out.write_all(b"  ")?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the name of `entry`, returning its width.
+    /// `start_col` is the column the name starts at.
+    /// `dired` records where the name is for `--dired`.
+    fn print_name(&mut self, entry: &Entry, start_col: usize, dired: bool) -> io::Result<usize> {
+        let color = self
+            .colors
+            .as_ref()
+            .and_then(|colors| colors.color_of(entry, false));
+
+        let quoted = self.quote(entry.name.as_bytes(), self.filename_quoting);
+        let absolute_name = entry.absolute_name.as_deref();
+        let dired = dired.then_some(DiredList::Names);
+        self.write_name(&quoted, color, absolute_name, start_col, dired)
+    }
+
+    fn print_link_target(&mut self, entry: &Entry, start_col: usize) -> io::Result<usize> {
+        let Some(target) = &entry.link_target else {
+            return Ok(0);
+        };
+
+        let color = self
+            .colors
+            .as_ref()
+            .and_then(|colors| colors.color_of(entry, true));
+
+        // only names are moved to line up with quoted ones
+        let mut quoted = self.quote(target.as_bytes(), self.filename_quoting);
+        quoted.pad = false;
+
+        let absolute_name = entry.absolute_name.as_deref();
+        self.write_name(&quoted, color, absolute_name, start_col, None)
+    }
+
+    /// Prints the name of `entry` with everything that goes along with it
+    /// in the short formats, returning the width of the name.
+    fn print_name_and_frills(
+        &mut self,
+        entry: &Entry,
+        start_col: usize,
+        frills: Frills,
+    ) -> io::Result<usize> {
+        self.set_normal_color()?;
+
+        let mut line = Vec::new();
+        self.push_frills(entry, frills, &mut line);
+        if self.cfg.flags.contains(LsFlags::PRINT_SECURIT_CTXT) {
+            pad_left(&mut line, context(entry), frills.context);
+            line.push(b' ');
+        }
+        self.out.write_all(&line)?;
+
+        let width = self.print_name(entry, start_col, false)?;
+
+        let indicator = self.type_indicator(entry.file_type, entry.mode());
+        Ok(width + self.print_indicator(indicator)?)
+    }
+
+    /// The character appended to a name for `--indicator-style`, `mode` is `None`
+    /// when only the type of the file is known.
+    fn type_indicator(&self, file_type: u32, mode: Option<u32>) -> Option<u8> {
+        let style = self.cfg.indicator;
+
+        match file_type {
+            _ if style == IndicatorStyle::None => None,
+            S_IFREG => (style == IndicatorStyle::Classify
+                && mode.is_some_and(|mode| mode & 0o111 != 0))
+            .then_some(b'*'),
+            S_IFDIR => Some(b'/'),
+            _ if style == IndicatorStyle::Slash => None,
+            S_IFLNK => Some(b'@'),
+            S_IFIFO => Some(b'|'),
+            S_IFSOCK => Some(b'='),
+            _ => None,
+        }
+    }
+
+    fn print_indicator(&mut self, indicator: Option<u8>) -> io::Result<usize> {
+        match indicator {
+            Some(indicator) => {
+                self.out.write_all(&[indicator])?;
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn set_normal_color(&mut self) -> io::Result<()> {
+        match &mut self.colors {
+            Some(colors) => colors.set_normal_color(&mut self.out.inner),
+            None => Ok(()),
+        }
+    }
+
+    fn write_name(
+        &mut self,
+        quoted: &Quoted,
+        color: Option<Color>,
+        absolute_name: Option<&OsStr>,
+        start_col: usize,
+        dired: Option<DiredList>,
+    ) -> io::Result<usize> {
+        let len = self.quote_name(quoted, color, absolute_name, dired)?;
+
+        if let Some(colors) = &mut self.colors
+            && (color.is_some() || colors.is_colored(Indicator::Norm))
+        {
+            colors.prep_non_filename_text(&mut self.out.inner)?;
+
+            // only the byte length is checked, a name that might wrap
+            // is enough to clear the rest of the line.
+            let line_width = self.cfg.width as usize;
+            if line_width != 0
+                && start_col / line_width != (start_col + len).saturating_sub(1) / line_width
+            {
+                colors.clear_to_eol(&mut self.out.inner)?;
+            }
+        }
+
+        Ok(len)
+    }
+
+    /// Whether `name` is printed any different than it is
+    fn needs_quoting(&self, name: &[u8]) -> bool {
+        let mut buf = Vec::with_capacity(name.len());
+        self.filename_quoting.quote(name, &mut buf);
+
+        buf != name
+    }
+
+    /// Quotes `name` for printing, hiding control characters with `-q`
+    pub(super) fn quote(&self, name: &[u8], quoting: Quoting) -> Quoted {
+        let mut buf = Vec::with_capacity(name.len());
+        quoting.quote(name, &mut buf);
+        let quoted = buf != name;
+
+        // -q only hides what the style leaves as it is
+        let hide_control_chars = self.cfg.flags.contains(LsFlags::HIDE_CONTROL_CHARS)
+            && matches!(
+                quoting.style,
+                QuotingStyle::Shell | QuotingStyle::ShellAlways | QuotingStyle::Literal
+            );
+
+        let width = if hide_control_chars {
+            quoting.hide_control_chars(&mut buf)
+        } else {
+            quoting.width(&buf)
+        };
+
+        let pad = self.align_quotes && self.some_quoted && !quoted;
+
+        Quoted {
+            buf,
+            width,
+            pad,
+            skip_quotes: self.align_quotes && self.some_quoted && !pad,
+        }
+    }
+
+    /// Prints a quoted name in `color`, linking it to `absolute_name` with `--hyperlink`
+    /// and recording where it is in `dired`. Returns its length with the padding.
+    pub(super) fn quote_name(
+        &mut self,
+        quoted: &Quoted,
+        color: Option<Color>,
+        absolute_name: Option<&OsStr>,
+        list: Option<DiredList>,
+    ) -> io::Result<usize> {
+        if quoted.pad {
+            self.out.write_all(b" ")?;
+        }
+
+        if let Some(colors) = &mut self.colors
+            && let Some(color) = color
+        {
+            colors.print_color(&mut self.out.inner, color)?;
+        }
+
+        let link = self.hyperlink_host.as_deref().zip(absolute_name);
+        let mut name = quoted.buf.as_slice();
+
+        if let Some((host, absolute_name)) = link {
+            // the outer quotes are left out of the link, so the links line up
+            if quoted.skip_quotes && name.len() >= 2 {
+                self.out.write_all(&name[..1])?;
+                name = &name[1..name.len() - 1];
+            }
+            hyperlink::open_link(&mut self.out.inner, host, absolute_name.as_bytes())?;
+        }
+
+        let start = self.out.pos;
+        self.out.write_all(name)?;
+
+        if let Some(dired) = &mut self.dired
+            && let Some(list) = list
+        {
+            dired.push(list, start, self.out.pos);
+        }
+
+        if link.is_some() {
+            hyperlink::close_link(&mut self.out.inner)?;
+            if name.len() != quoted.buf.len() {
+                self.out.write_all(&quoted.buf[quoted.buf.len() - 1..])?;
+            }
+        }
+
+        Ok(quoted.buf.len() + quoted.pad as usize)
+    }
+
+    /// The columns the name of `entry` takes up once quoted, for `--sort=width`
+    pub(super) fn quoted_width(&self, entry: &Entry) -> usize {
+        self.quote(entry.name.as_bytes(), self.filename_quoting)
+            .width
+    }
+
+    fn name_width(&self, entry: &Entry, frills: Frills) -> usize {
+        let indicator = self.type_indicator(entry.file_type, entry.mode());
+        let quoted = self.quote(entry.name.as_bytes(), self.filename_quoting);
+
+        self.frills_width(frills)
+            + quoted.width
+            + quoted.pad as usize
+            + indicator.is_some() as usize
+    }
+
+    fn print_single_column(&mut self, entries: &[Entry], frills: Frills) -> io::Result<()> {
+        for entry in entries {
+            self.print_name_and_frills(entry, 0, frills)?;
+            self.out.write_all(&[self.eol()])?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints names separated by commas, starting a new line
+    /// before one that would not fit.
+    fn print_with_commas(&mut self, entries: &[Entry]) -> io::Result<()> {
+        let line_width = self.cfg.width as usize;
+        let mut pos = 0;
+
+        for (index, entry) in entries.iter().enumerate() {
+            let len = if line_width == 0 {
+                0
+            } else {
+                self.name_width(entry, self.entry_frills(entry))
+            };
+
+            if index != 0 {
+                let separator = if line_width == 0 || pos + len + 2 < line_width {
+                    pos += 2;
+                    b' '
+                } else {
+                    pos = 0;
+                    self.eol()
+                };
+
+                self.out.write_all(&[b',', separator])?;
+            }
+
+            // the frills aren't aligned, so they are as wide as they need to be
+            self.print_name_and_frills(entry, pos, Frills::default())?;
+            pos += len;
+        }
+
+        self.out.write_all(&[self.eol()])
+    }
+
+    /// Lays out names in as many columns as fit in the line width, filling
+    /// columns first when `vertical` is set and rows first otherwise.
+    fn print_columns(
+        &mut self,
+        entries: &[Entry],
+        frills: Frills,
+        vertical: bool,
+    ) -> io::Result<()> {
+        let widths: Vec<usize> = entries
+            .iter()
+            .map(|entry| self.name_width(entry, frills))
+            .collect();
+        let col_widths = self.calculate_columns(&widths, vertical);
+        let cols = col_widths.len();
+        let rows = entries.len().div_ceil(cols);
+
+        for row in 0..rows {
+            let mut pos = 0;
+            let mut col = 0;
+
+            loop {
+                let index = if vertical {
+                    row + col * rows
+                } else {
+                    row * cols + col
+                };
+                self.print_name_and_frills(&entries[index], pos, frills)?;
+
+                let next = if vertical { index + rows } else { index + 1 };
+                if next >= entries.len() || (!vertical && col + 1 == cols) {
+                    break;
+                }
+
+                self.indent(pos + widths[index], pos + col_widths[col])?;
+                pos += col_widths[col];
+                col += 1;
+            }
+
+            self.out.write_all(&[self.eol()])?;
+        }
+
+        Ok(())
+    }
+
+    /// Finds the largest number of columns that fits, returning the width of each
+    fn calculate_columns(&self, widths: &[usize], vertical: bool) -> Vec<usize> {
+        let line_width = self.cfg.width as usize;
+        let max_idx = if line_width == 0 {
+            widths.len()
+        } else {
+            line_width.div_ceil(MIN_COLUMN_WIDTH).max(1)
+        };
+        let max_cols = max_idx.min(widths.len()).max(1);
+
+        let mut layouts: Vec<(bool, usize, Vec<usize>)> = (1..=max_cols)
+            .map(|cols| (true, cols * MIN_COLUMN_WIDTH, vec![MIN_COLUMN_WIDTH; cols]))
+            .collect();
+
+        for (index, &width) in widths.iter().enumerate() {
+            for (i, (valid, line_len, col_arr)) in layouts.iter_mut().enumerate() {
+                if !*valid {
+                    continue;
+                }
+
+                let cols = i + 1;
+                let idx = if vertical {
+                    index / widths.len().div_ceil(cols)
+                } else {
+                    index % cols
+                };
+
+                let real_width = width + if idx == i { 0 } else { 2 };
+
+                if col_arr[idx] < real_width {
+                    *line_len += real_width - col_arr[idx];
+                    col_arr[idx] = real_width;
+                    *valid = line_width == 0 || *line_len < line_width;
+                }
+            }
+        }
+
+        let cols = (1..=max_cols)
+            .rev()
+            .find(|&cols| cols == 1 || layouts[cols - 1].0)
+            .unwrap_or(1);
+
+        layouts.swap_remove(cols - 1).2
+    }
+
+    fn indent(&mut self, mut from: usize, to: usize) -> io::Result<()> {
+        let tab_size = self.cfg.tab_size;
+
+        while from < to {
+            if tab_size != 0 && to / tab_size > (from + 1) / tab_size {
+                self.out.write_all(b"\t")?;
+                from += tab_size - from % tab_size;
+            } else {
+                self.out.write_all(b" ")?;
+                from += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn print_long(
+        &mut self,
+        entries: &[Entry],
+        extracted: &[Entry],
+        frills: Frills,
+    ) -> io::Result<()> {
+        struct Row {
+            nlink: Vec<u8>,
+            owner: Id,
+            group: Id,
+            size: Vec<u8>,
+            major_minor: Option<(Vec<u8>, Vec<u8>)>,
+            date: Vec<u8>,
+        }
+
+        let print_owner = !self.cfg.flags.contains(LsFlags::NO_OWNER_LISTED);
+        let print_group = !self.cfg.flags.contains(LsFlags::NO_GROUPS_LISTED);
+        // the author is always the owner on Linux
+        let print_author = self.cfg.flags.contains(LsFlags::PRINT_AUTHOR);
+        let print_context = self.cfg.flags.contains(LsFlags::PRINT_SECURIT_CTXT);
+        let any_acl = entries
+            .iter()
+            .chain(extracted)
+            .any(|entry| entry.acl_marker().is_some());
+        let (size_opts, size_unit) = self.cfg.file_size;
+
+        let mut rows = Vec::with_capacity(entries.len() + extracted.len());
+
+        for entry in entries.iter().chain(extracted) {
+            let Some(meta) = &entry.meta else {
+                rows.push(Row {
+                    nlink: b"?".to_vec(),
+                    owner: Id::unknown(),
+                    group: Id::unknown(),
+                    size: b"?".to_vec(),
+                    major_minor: None,
+                    date: self.unknown_time(),
+                });
+                continue;
+            };
+
+            let mut buf = itoa::Buffer::new();
+
+            let numeric = self.cfg.flags.contains(LsFlags::NUMERIC_IDS);
+
+            let owner = if print_owner || print_author {
+                let name = (!numeric).then(|| self.names.user_name(meta.uid)).flatten();
+                Id::new(name, meta.uid)
+            } else {
+                Id::unknown()
+            };
+
+            let group = if print_group {
+                let name = (!numeric)
+                    .then(|| self.names.group_name(meta.gid))
+                    .flatten();
+                Id::new(name, meta.gid)
+            } else {
+                Id::unknown()
+            };
+
+            let file_type = meta.file_type();
+            let major_minor = (file_type == S_IFCHR || file_type == S_IFBLK).then(|| {
+                let (major, minor) = meta.rdev;
+
+                (
+                    buf.format(major).as_bytes().to_vec(),
+                    buf.format(minor).as_bytes().to_vec(),
+                )
+            });
+
+            let mut size = Vec::new();
+            if major_minor.is_none() {
+                human_readable(meta.size, 1, size_unit, size_opts, &mut size);
+            }
+
+            rows.push(Row {
+                nlink: buf.format(meta.nlink).as_bytes().to_vec(),
+                owner,
+                group,
+                size,
+                major_minor,
+                date: match entry.time(self.cfg.time) {
+                    Some((seconds, nanoseconds)) => self.format_time(seconds, nanoseconds as u32),
+                    None => self.unknown_time(),
+                },
+            });
+        }
+
+        let nlink_width = rows.iter().map(|row| row.nlink.len()).max().unwrap_or(0);
+        let owner_width = rows
+            .iter()
+            .map(|row| row.owner.text.len())
+            .max()
+            .unwrap_or(0);
+        let group_width = rows
+            .iter()
+            .map(|row| row.group.text.len())
+            .max()
+            .unwrap_or(0);
+        let major_width = rows
+            .iter()
+            .filter_map(|row| row.major_minor.as_ref().map(|(major, _)| major.len()))
+            .max()
+            .unwrap_or(0);
+        let minor_width = rows
+            .iter()
+            .filter_map(|row| row.major_minor.as_ref().map(|(_, minor)| minor.len()))
+            .max()
+            .unwrap_or(0);
+        let device_width = if major_width == 0 {
+            0
+        } else {
+            major_width + 2 + minor_width
+        };
+        let size_width = rows
+            .iter()
+            .map(|row| row.size.len())
+            .max()
+            .unwrap_or(0)
+            .max(device_width);
+
+        let mut line = Vec::new();
+
+        for (entry, row) in entries.iter().zip(&rows) {
+            line.clear();
+            self.push_frills(entry, frills, &mut line);
+
+            match &entry.meta {
+                Some(meta) => line.extend_from_slice(&filemode(meta.mode)),
+                None => {
+                    line.push(filemode(entry.file_type)[0]);
+                    line.extend_from_slice(b"?????????");
+                }
+            }
+            if any_acl {
+                let unknown = entry.meta.is_none().then_some(b'?');
+                line.push(entry.acl_marker().or(unknown).unwrap_or(b' '));
+            }
+            line.push(b' ');
+
+            pad_left(&mut line, &row.nlink, nlink_width);
+            line.push(b' ');
+
+            if print_owner {
+                row.owner.push(&mut line, owner_width);
+            }
+
+            if print_group {
+                row.group.push(&mut line, group_width);
+            }
+
+            if print_author {
+                row.owner.push(&mut line, owner_width);
+            }
+
+            if print_context {
+                pad_right(&mut line, context(entry), frills.context);
+                line.push(b' ');
+            }
+
+            match &row.major_minor {
+                Some((major, minor)) => {
+                    let blanks = size_width.saturating_sub(device_width);
+                    pad_left(&mut line, major, major_width + blanks);
+                    line.extend_from_slice(b", ");
+                    pad_left(&mut line, minor, minor_width);
+                }
+                None => pad_left(&mut line, &row.size, size_width),
+            }
+            line.push(b' ');
+
+            line.extend_from_slice(&row.date);
+            line.push(b' ');
+
+            self.set_normal_color()?;
+            self.dired_indent()?;
+            self.out.write_all(&line)?;
+
+            let width = self.print_name(entry, line.len(), true)?;
+
+            if entry.file_type == S_IFLNK {
+                // symlinks get the indicator of the file they point to
+                if entry.link_target.is_some() {
+                    self.out.write_all(b" -> ")?;
+                    self.print_link_target(entry, line.len() + width + 4)?;
+
+                    let link_mode = entry.link_meta.as_ref().map_or(0, |meta| meta.mode);
+                    let indicator = self.type_indicator(link_mode & S_IFMT, Some(link_mode));
+                    self.print_indicator(indicator)?;
+                }
+            } else {
+                self.print_indicator(self.type_indicator(entry.file_type, entry.mode()))?;
+            }
+
+            self.out.write_all(&[self.eol()])?;
+        }
+
+        Ok(())
+    }
+
+    fn format_time(&mut self, seconds: i64, nanoseconds: u32) -> Vec<u8> {
+        let when = (seconds, nanoseconds as i64);
+
+        if (self.now.tv_sec, self.now.tv_nsec) < when
+            && let Ok(now) = clock_gettime(ClockId::Realtime)
+        {
+            self.now = now;
+        }
+
+        let now = (self.now.tv_sec, self.now.tv_nsec);
+        let six_months_ago = (now.0 - SIX_MONTHS, now.1);
+        let recent = six_months_ago < when && when < now;
+
+        let [old_format, recent_format] = self.cfg.time_ty.formats();
+
+        let mut buf = Vec::new();
+        self.tz
+            .to_local(seconds, nanoseconds)
+            .format(if recent { recent_format } else { old_format }, &mut buf);
+
+        buf
+    }
+
+    /// A `?` in place of a timestamp, as wide as a formatted one is expected to be
+    fn unknown_time(&self) -> Vec<u8> {
+        let mut expected = Vec::new();
+        let [old_format, _] = self.cfg.time_ty.formats();
+        self.tz.to_local(0, 0).format(old_format, &mut expected);
+
+        let mut buf = Vec::new();
+        pad_left(&mut buf, b"?", expected.len());
+
+        buf
+    }
+}
+
+/// Widths of the columns printed before names in every format
+#[derive(Clone, Copy, Default)]
+struct Frills {
+    inode: usize,
+    blocks: usize,
+    context: usize,
+}
+
+/// The security context shown by `-Z`
+/// A name as it is printed
+pub(super) struct Quoted {
+    buf: Vec<u8>,
+
+    /// columns taken up, without the padding
+    width: usize,
+
+    /// whether a space goes before the name, lining it up with names in quotes
+    pad: bool,
+
+    /// whether the outer quotes are left out of hyperlinks
+    skip_quotes: bool,
+}
+
+/// An owner or group in long listings
+struct Id {
+    text: Vec<u8>,
+
+    /// ids without a name are aligned to the right, like numbers are
+    numeric: bool,
+}
+
+impl Id {
+    /// For files that couldn't be `stat`ed
+    fn unknown() -> Self {
+        Self {
+            text: b"?".to_vec(),
+            numeric: false,
+        }
+    }
+
+    fn new(name: Option<&[u8]>, id: u32) -> Self {
+        match name {
+            Some(name) => Self {
+                text: name.to_vec(),
+                numeric: false,
+            },
+            None => Self {
+                text: itoa::Buffer::new().format(id).as_bytes().to_vec(),
+                numeric: true,
+            },
+        }
+    }
+
+    /// Appends the id padded to `width`, and the space after it
+    fn push(&self, line: &mut Vec<u8>, width: usize) {
+        if self.numeric {
+            pad_left(line, &self.text, width);
+        } else {
+            pad_right(line, &self.text, width);
+        }
+        line.push(b' ');
+    }
+}
+
+fn context(entry: &Entry) -> &[u8] {
+    entry.context.as_deref().unwrap_or(b"?")
+}
+
+/// Files that failed to be `stat`ed have an inode number of 0
+fn push_inode(entry: &Entry, out: &mut Vec<u8>) {
+    match entry.ino {
+        0 => out.push(b'?'),
+        ino => out.extend_from_slice(itoa::Buffer::new().format(ino).as_bytes()),
+    }
+}
+
+/// Renders a mode the way `ls -l` does, e.g. `drwxr-xr-x`
+fn filemode(mode: u32) -> [u8; 10] {
+    let file_type = match mode & S_IFMT {
+        S_IFREG => b'-',
+        S_IFDIR => b'd',
+        S_IFLNK => b'l',
+        S_IFBLK => b'b',
+        S_IFCHR => b'c',
+        S_IFIFO => b'p',
+        S_IFSOCK => b's',
+        _ => b'?',
+    };
+
+    let bit = |mask: u32, set: u8| if mode & mask != 0 { set } else { b'-' };
+    let special = |exec: u32, special: u32, lower: u8, upper: u8| match (
+        mode & exec != 0,
+        mode & special != 0,
+    ) {
+        (true, true) => lower,
+        (false, true) => upper,
+        (true, false) => b'x',
+        (false, false) => b'-',
+    };
+
+    [
+        file_type,
+        bit(0o400, b'r'),
+        bit(0o200, b'w'),
+        special(0o100, 0o4000, b's', b'S'),
+        bit(0o040, b'r'),
+        bit(0o020, b'w'),
+        special(0o010, 0o2000, b's', b'S'),
+        bit(0o004, b'r'),
+        bit(0o002, b'w'),
+        special(0o001, 0o1000, b't', b'T'),
+    ]
+}
+
+fn pad_left(line: &mut Vec<u8>, text: &[u8], width: usize) {
+    line.resize(line.len() + width.saturating_sub(text.len()), b' ');
+    line.extend_from_slice(text);
+}
+
+fn pad_right(line: &mut Vec<u8>, text: &[u8], width: usize) {
+    line.extend_from_slice(text);
+    line.resize(line.len() + width.saturating_sub(text.len()), b' ');
+}
@@ -0,0 +1,425 @@
+use super::options::QuotingStyle;
+use puppyutils::utf8::{self, Decoded};
+
+/// How names are quoted, following gnulib's `quotearg` in the C locale
+/// or in a UTF-8 one
+#[derive(Clone, Copy)]
+pub(crate) struct Quoting {
+    pub(crate) style: QuotingStyle,
+
+    /// whether names are taken as UTF-8, otherwise only ASCII is printable
+    pub(crate) utf8: bool,
+
+    /// characters that get quoted on top of what the style quotes
+    quote_these_too: [u32; 8],
+}
+
+impl Quoting {
+    pub(crate) const fn new(style: QuotingStyle, utf8: bool) -> Self {
+        Self {
+            style,
+            utf8,
+            quote_these_too: [0; 8],
+        }
+    }
+
+    /// Also quotes `c`, e.g. the `:` that ends directory headers
+    pub(crate) const fn with_char(mut self, c: u8) -> Self {
+        self.quote_these_too[c as usize / 32] |= 1 << (c % 32);
+        self
+    }
+
+    /// Appends `name` quoted to `out`
+    pub(crate) fn quote(&self, name: &[u8], out: &mut Vec<u8>) {
+        let start = out.len();
+
+        if let Err(style) = quote_restyled(
+            name,
+            self.style,
+            self.utf8,
+            Some(&self.quote_these_too),
+            out,
+        ) {
+            // the outer quotes were left out but turned out to be needed,
+            // and they quote everything the extra characters would need
+            out.truncate(start);
+            let _ = quote_restyled(name, style, self.utf8, None, out);
+        }
+    }
+
+    /// The columns a quoted name takes up on a terminal, like gnulib's `mbsnwidth`
+    /// in a UTF-8 locale, otherwise GNU `ls` only counts the printable bytes
+    pub(crate) fn width(&self, quoted: &[u8]) -> usize {
+        if !self.utf8 {
+            return quoted.iter().filter(|&&c| is_print(c)).count();
+        }
+
+        let mut width = 0;
+        let mut rest = quoted;
+
+        while !rest.is_empty() {
+            let len = match utf8::decode(rest) {
+                Decoded::Char(c, len) => {
+                    width += utf8::width(c);
+                    len
+                }
+                Decoded::Invalid => {
+                    width += 1;
+                    1
+                }
+                Decoded::Incomplete => {
+                    width += 1;
+                    rest.len()
+                }
+            };
+            rest = &rest[len..];
+        }
+
+        width
+    }
+
+    /// Replaces what can't be printed in a quoted name with `?`, like `ls -q`,
+    /// returning its width. A character takes one `?`, bytes that aren't one
+    /// take one each, and the start of a character cut off at the end one for all.
+    pub(crate) fn hide_control_chars(&self, quoted: &mut Vec<u8>) -> usize {
+        if !self.utf8 {
+            for c in quoted.iter_mut() {
+                if !is_print(*c) {
+                    *c = b'?';
+                }
+            }
+            return quoted.len();
+        }
+
+        let mut hidden = Vec::with_capacity(quoted.len());
+        let mut width = 0;
+        let mut rest = quoted.as_slice();
+
+        while !rest.is_empty() {
+            let len = match utf8::decode(rest) {
+                Decoded::Char(c, len) if utf8::is_print(c) => {
+                    hidden.extend_from_slice(&rest[..len]);
+                    width += utf8::width(c);
+                    len
+                }
+                Decoded::Char(_, len) => {
+                    hidden.push(b'?');
+                    width += 1;
+                    len
+                }
+                Decoded::Invalid => {
+                    hidden.push(b'?');
+                    width += 1;
+                    1
+                }
+                Decoded::Incomplete => {
+                    hidden.push(b'?');
+                    width += 1;
+                    rest.len()
+                }
+            };
+            rest = &rest[len..];
+        }
+
+        *quoted = hidden;
+        width
+    }
+}
+
+/// Whether a byte is printable in the C locale
+#[inline]
+pub(crate) fn is_print(c: u8) -> bool {
+    (b' '..=b'~').contains(&c)
+}
+
+enum Store {
+    /// store the character as it is, unless it is one of the extra characters to quote
+    Char,
+    /// store the character after a backslash
+    Escape,
+    /// store the character as it is
+    Plain,
+}
+
+/// One pass of `quotearg_buffer_restyled`, `Err` holds the style to start over with
+/// when a style that leaves out the outer quotes finds a name that needs them.
+fn quote_restyled(
+    arg: &[u8],
+    style: QuotingStyle,
+    utf8: bool,
+    quote_these_too: Option<&[u32; 8]>,
+    out: &mut Vec<u8>,
+) -> Result<(), QuotingStyle> {
+    let start = out.len();
+
+    let mut style = style;
+    let mut backslash_escapes = false;
+    let mut elide_outer_quotes = false;
+    let mut quote_string: &[u8] = b"";
+
+    match style {
+        QuotingStyle::CMaybe | QuotingStyle::C => {
+            elide_outer_quotes = style == QuotingStyle::CMaybe;
+            style = QuotingStyle::C;
+            if !elide_outer_quotes {
+                out.push(b'"');
+            }
+            backslash_escapes = true;
+            quote_string = b"\"";
+        }
+        QuotingStyle::Escape => backslash_escapes = true,
+        QuotingStyle::Locale | QuotingStyle::Clocale => {
+            // what the locale translates the quotes to, gnulib
+            // picks the curly ones for UTF-8 locales
+            let (left_quote, right_quote): (&[u8], &[u8]) = match style {
+                // U+2018 and U+2019
+                _ if utf8 => (b"\xe2\x80\x98", b"\xe2\x80\x99"),
+                QuotingStyle::Locale => (b"'", b"'"),
+                _ => (b"\"", b"\""),
+            };
+            quote_string = right_quote;
+            out.extend_from_slice(left_quote);
+            backslash_escapes = true;
+        }
+        QuotingStyle::Shell
+        | QuotingStyle::ShellEscape
+        | QuotingStyle::ShellAlways
+        | QuotingStyle::ShellEscapeAlways => {
+            elide_outer_quotes = matches!(style, QuotingStyle::Shell | QuotingStyle::ShellEscape);
+            backslash_escapes = matches!(
+                style,
+                QuotingStyle::ShellEscape | QuotingStyle::ShellEscapeAlways
+            );
+            style = QuotingStyle::ShellAlways;
+            if !elide_outer_quotes {
+                out.push(b'\'');
+            }
+            quote_string = b"'";
+        }
+        QuotingStyle::Literal => {}
+    }
+
+    let shell = style == QuotingStyle::ShellAlways;
+    let force_outer_quotes = if shell && backslash_escapes {
+        QuotingStyle::ShellEscapeAlways
+    } else {
+        style
+    };
+
+    let mut pending_shell_escape_end = false;
+    let mut encountered_single_quote = false;
+    let mut all_c_and_shell_quote_compat = true;
+
+    let mut i = 0;
+    while i < arg.len() {
+        let mut c = arg[i];
+        let mut is_right_quote = false;
+        let mut escaping = false;
+        let mut c_and_shell_quote_compat = false;
+
+        if backslash_escapes
+            && !shell
+            && !quote_string.is_empty()
+            && arg[i..].starts_with(quote_string)
+        {
+            if elide_outer_quotes {
+                return Err(force_outer_quotes);
+            }
+            is_right_quote = true;
+        }
+
+        let store = match c {
+            b'?' if shell && elide_outer_quotes => return Err(force_outer_quotes),
+            b'?' => Store::Char,
+
+            b'\\' if shell => {
+                if elide_outer_quotes {
+                    return Err(force_outer_quotes);
+                }
+                Store::Plain
+            }
+            // nothing else needs escaping if the outer quotes may be left out
+            b'\\' if backslash_escapes && elide_outer_quotes && !quote_string.is_empty() => {
+                Store::Plain
+            }
+            b'\\' if backslash_escapes => Store::Escape,
+            b'\\' => Store::Char,
+
+            0x07 | 0x08 | 0x0b | 0x0c | b'\n' | b'\r' | b'\t' => {
+                if matches!(c, b'\n' | b'\r' | b'\t') && shell && elide_outer_quotes {
+                    return Err(force_outer_quotes);
+                }
+
+                if backslash_escapes {
+                    c = match c {
+                        0x07 => b'a',
+                        0x08 => b'b',
+                        0x0b => b'v',
+                        0x0c => b'f',
+                        b'\n' => b'n',
+                        b'\r' => b'r',
+                        _ => b't',
+                    };
+                    Store::Escape
+                } else {
+                    Store::Char
+                }
+            }
+
+            // sometimes special when on their own or at the start
+            b'{' | b'}' if arg.len() != 1 => Store::Char,
+            b'{' | b'}' | b'#' | b'~' if i != 0 => Store::Char,
+            b'{' | b'}' | b'#' | b'~' | b' ' => {
+                c_and_shell_quote_compat = true;
+                if shell && elide_outer_quotes {
+                    return Err(force_outer_quotes);
+                }
+                Store::Char
+            }
+            b'!' | b'"' | b'$' | b'&' | b'(' | b')' | b'*' | b';' | b'<' | b'=' | b'>' | b'['
+            | b'^' | b'`' | b'|' => {
+                if shell && elide_outer_quotes {
+                    return Err(force_outer_quotes);
+                }
+                Store::Char
+            }
+
+            b'\'' => {
+                encountered_single_quote = true;
+                c_and_shell_quote_compat = true;
+
+                if shell {
+                    if elide_outer_quotes {
+                        return Err(force_outer_quotes);
+                    }
+
+                    out.extend_from_slice(b"'\\'");
+                    pending_shell_escape_end = false;
+                }
+                Store::Char
+            }
+
+            b'%'
+            | b'+'
+            | b','
+            | b'-'
+            | b'.'
+            | b'/'
+            | b'0'..=b'9'
+            | b':'
+            | b'A'..=b'Z'
+            | b']'
+            | b'_'
+            | b'a'..=b'z' => {
+                c_and_shell_quote_compat = true;
+                Store::Char
+            }
+
+            _ => {
+                // a character is taken as a whole, unprintable when it isn't valid,
+                // and the start of one cut off at the end takes the rest with it
+                let (len, printable) = if !utf8 || c.is_ascii() {
+                    (1, is_print(c))
+                } else {
+                    match utf8::decode(&arg[i..]) {
+                        Decoded::Char(wc, len) => (len, utf8::is_print(wc)),
+                        Decoded::Invalid => (1, false),
+                        Decoded::Incomplete => (arg.len() - i, false),
+                    }
+                };
+                c_and_shell_quote_compat = printable;
+
+                if len > 1 || (backslash_escapes && !printable) {
+                    // all bytes but the last, which is stored below
+                    let last = i + len - 1;
+                    loop {
+                        if backslash_escapes && !printable {
+                            // an octal escape of each byte
+                            if elide_outer_quotes {
+                                return Err(force_outer_quotes);
+                            }
+                            escaping = true;
+                            start_escape(shell, &mut pending_shell_escape_end, out);
+                            out.push(b'0' + (c >> 6));
+                            out.push(b'0' + ((c >> 3) & 7));
+                            c = b'0' + (c & 7);
+                        } else if is_right_quote {
+                            out.push(b'\\');
+                            is_right_quote = false;
+                        }
+
+                        if i == last {
+                            break;
+                        }
+
+                        if pending_shell_escape_end && !escaping {
+                            out.extend_from_slice(b"''");
+                            pending_shell_escape_end = false;
+                        }
+                        out.push(c);
+                        i += 1;
+                        c = arg[i];
+                    }
+                    Store::Plain
+                } else {
+                    Store::Char
+                }
+            }
+        };
+
+        let escape = match store {
+            Store::Plain => false,
+            Store::Escape => true,
+            Store::Char => {
+                let extra =
+                    quote_these_too.is_some_and(|set| set[c as usize / 32] >> (c % 32) & 1 != 0);
+
+                (((backslash_escapes && !shell) || elide_outer_quotes) && extra) || is_right_quote
+            }
+        };
+
+        if escape {
+            if elide_outer_quotes {
+                return Err(force_outer_quotes);
+            }
+            escaping = true;
+            start_escape(shell, &mut pending_shell_escape_end, out);
+        }
+
+        if pending_shell_escape_end && !escaping {
+            out.extend_from_slice(b"''");
+            pending_shell_escape_end = false;
+        }
+        out.push(c);
+
+        if !c_and_shell_quote_compat {
+            all_c_and_shell_quote_compat = false;
+        }
+        i += 1;
+    }
+
+    if out.len() == start && shell && elide_outer_quotes {
+        return Err(force_outer_quotes);
+    }
+
+    // names like "it's" read better in double quotes
+    if shell && !elide_outer_quotes && encountered_single_quote && all_c_and_shell_quote_compat {
+        out.truncate(start);
+        return quote_restyled(arg, QuotingStyle::C, utf8, quote_these_too, out);
+    }
+
+    if !elide_outer_quotes {
+        out.extend_from_slice(quote_string);
+    }
+
+    Ok(())
+}
+
+/// Starts a backslash escape, which needs `$'...'` quoting in the shell styles
+fn start_escape(shell: bool, pending_shell_escape_end: &mut bool, out: &mut Vec<u8>) {
+    if shell && !*pending_shell_escape_end {
+        out.extend_from_slice(b"'$'");
+        *pending_shell_escape_end = true;
+    }
+    out.push(b'\\');
+}
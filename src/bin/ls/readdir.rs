@@ -0,0 +1,119 @@
+use std::{
+    ffi::OsString,
+    fs::{File, OpenOptions},
+    io,
+    os::{
+        fd::AsRawFd,
+        unix::{ffi::OsStringExt, fs::OpenOptionsExt},
+    },
+    path::Path,
+};
+
+use super::entry::{S_IFBLK, S_IFCHR, S_IFDIR, S_IFIFO, S_IFLNK, S_IFREG, S_IFSOCK};
+
+const BUFFER_SIZE: usize = 32 * 1024;
+
+/// A file found in a directory, with what the directory itself knows about it
+pub(crate) struct DirEntry {
+    pub(crate) name: OsString,
+    pub(crate) ino: u64,
+    /// the `S_IF*` bits of the type, 0 if the file system doesn't tell
+    pub(crate) file_type: u32,
+}
+
+/// Reads a directory with `getdents64`. Unlike `fs::read_dir`, `.` and `..`
+/// are kept where the file system puts them, and nothing is `stat`ed.
+pub(crate) struct ReadDir {
+    dir: File,
+    buf: Vec<u8>,
+    pos: usize,
+    len: usize,
+    done: bool,
+}
+
+impl ReadDir {
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        let dir = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_DIRECTORY)
+            .open(path)?;
+
+        Ok(Self {
+            dir,
+            buf: vec![0; BUFFER_SIZE],
+            pos: 0,
+            len: 0,
+            done: false,
+        })
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        // SAFETY: `dir` is an open directory and the kernel writes at most
+        // `buf.len()` bytes of entries into `buf`
+        let len = unsafe {
+            libc::syscall(
+                libc::SYS_getdents64,
+                self.dir.as_raw_fd(),
+                self.buf.as_mut_ptr(),
+                self.buf.len(),
+            )
+        };
+
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        self.pos = 0;
+        self.len = len as usize;
+        self.done = len == 0;
+
+        Ok(())
+    }
+}
+
+impl Iterator for ReadDir {
+    type Item = io::Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos >= self.len {
+            if self.done {
+                return None;
+            }
+
+            if let Err(err) = self.fill() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+
+        // struct linux_dirent64: a u64 inode, an i64 offset, a u16 record length,
+        // a u8 type and the name ending with a NUL
+        let record = &self.buf[self.pos..self.len];
+        let ino = u64::from_ne_bytes(record[..8].try_into().unwrap());
+        let reclen = u16::from_ne_bytes(record[16..18].try_into().unwrap()) as usize;
+        let d_type = record[18];
+        let name = &record[19..reclen];
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+
+        self.pos += reclen;
+
+        Some(Ok(DirEntry {
+            name: OsString::from_vec(name.to_vec()),
+            ino,
+            file_type: type_bits(d_type),
+        }))
+    }
+}
+
+fn type_bits(d_type: u8) -> u32 {
+    match d_type {
+        libc::DT_BLK => S_IFBLK,
+        libc::DT_CHR => S_IFCHR,
+        libc::DT_DIR => S_IFDIR,
+        libc::DT_FIFO => S_IFIFO,
+        libc::DT_LNK => S_IFLNK,
+        libc::DT_REG => S_IFREG,
+        libc::DT_SOCK => S_IFSOCK,
+        _ => 0,
+    }
+}
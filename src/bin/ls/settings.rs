@@ -1,17 +1,116 @@
 #![allow(dead_code)]
-use super::options::*;
-use puppyutils::{Result, cli_with_args};
+use super::{
+    human::{self, BlockSizeError, HumanOpts},
+    options::*,
+};
+use puppyutils::{Result, argmatch, args::Args, cli_with_args, missing_argument};
 use sap::Parser;
-use std::io;
+use std::{env, io, process::exit};
 
-const DEFAULT_BLOCK_SIZE: usize = 512;
+const DEFAULT_BLOCK_SIZE: u64 = 1024;
+const POSIX_BLOCK_SIZE: u64 = 512;
+const DEFAULT_TAB_SIZE: usize = 8;
 
-fn needs_an_argument() -> ! {
-    todo!()
+/// Exits like `getopt` does when `option` is given without its argument
+fn needs_an_argument(program: &str, option: &str) -> ! {
+    missing_argument(program, option);
+    exit(2)
 }
 
-fn invalid_argument() -> ! {
-    todo!()
+fn invalid_time_style(program: &str, style: &str) -> ! {
+    eprintln!("{program}: invalid argument '{style}' for 'time style'");
+    eprintln!("Valid arguments are:");
+
+    for style in ["full-iso", "long-iso", "iso", "locale"] {
+        eprintln!("  - [posix-]{style}");
+    }
+
+    eprintln!("  - +FORMAT (e.g., +%H:%M) for a 'date'-style format");
+    eprintln!("Try '{program} --help' for more information.");
+    exit(2)
+}
+
+fn invalid_block_size(program: &str, arg: &str, err: BlockSizeError) -> ! {
+    match err {
+        BlockSizeError::Invalid => eprintln!("{program}: invalid --block-size argument '{arg}'"),
+        BlockSizeError::InvalidSuffix => {
+            eprintln!("{program}: invalid suffix in --block-size argument '{arg}'")
+        }
+        BlockSizeError::TooLarge => {
+            eprintln!("{program}: --block-size argument '{arg}' too large")
+        }
+    }
+
+    exit(2)
+}
+
+/// The argument of an option like `--color`, which is `always` when left out
+fn optional_when(args: &mut Parser<Args>, program: &str, option: &str) -> When {
+    if !args.has_leftover_value() {
+        return When::Always;
+    }
+
+    let when = args.value().unwrap_or_default();
+    argmatch(program, option, &when, When::NAMES)
+}
+
+/// The distance between tab stops from `-T`, 0 means no tabs
+fn parse_tab_size(program: &str, size: Option<String>, option: &str) -> usize {
+    let Some(size) = size else {
+        needs_an_argument(program, option);
+    };
+
+    match size.parse() {
+        Ok(size) => size,
+        Err(_) => {
+            eprintln!("{program}: invalid tab size: '{size}'");
+            exit(2)
+        }
+    }
+}
+
+/// The width from `-w`, 0 means no limit
+fn parse_width(program: &str, width: Option<String>, option: &str) -> u16 {
+    let Some(width) = width else {
+        needs_an_argument(program, option);
+    };
+
+    match width.parse() {
+        Ok(width) => width,
+        Err(_) => {
+            eprintln!("{program}: invalid line width: '{width}'");
+            exit(2)
+        }
+    }
+}
+
+/// The block size from `spec` or the environment, anything invalid is ignored
+fn env_block_size(spec: Option<String>) -> (HumanOpts, u64) {
+    let default = if env::var_os("POSIXLY_CORRECT").is_some() {
+        POSIX_BLOCK_SIZE
+    } else {
+        DEFAULT_BLOCK_SIZE
+    };
+
+    spec.or_else(|| env::var("BLOCK_SIZE").ok())
+        .or_else(|| env::var("BLOCKSIZE").ok())
+        .and_then(|spec| human::parse_block_size(&spec).ok())
+        .unwrap_or((HumanOpts::EXACT, default))
+}
+
+/// The quoting style from `QUOTING_STYLE`, invalid values are warned about and ignored
+fn env_quoting_style(program: &str) -> Option<QuotingStyle> {
+    let style = env::var_os("QUOTING_STYLE")?;
+    let parsed = QuotingStyle::from_bytes(style.as_encoded_bytes());
+
+    if parsed.is_none() {
+        eprintln!(
+            "{program}: ignoring invalid value of environment variable QUOTING_STYLE: '{}'",
+            style.to_string_lossy()
+        );
+    }
+
+    parsed
 }
 
 bitflags::bitflags! {
@@ -21,22 +120,16 @@ bitflags::bitflags! {
         const IGNORE_DOTS_EXCEPT_DIRS =  1 << 1;  // -A --almost-all
         const PRINT_AUTHOR =             1 << 2;  // --author
         const LIST_DIRECTORIES =         1 << 3;  // -d -- directory
-        const C_STYLE_ESCAPED =          1 << 4;  // -b --escape
         const IGNORE_TILDE_ENTRIES =     1 << 5;  // -B --ignore-backups
         const LIST_BY_COLUMNS =          1 << 6;  // -C
         const EMACS_DIRED_MODE =         1 << 7;  // -D --dired
         const SORT_ENTRIES =             1 << 8;  // partially -f (can disable it) and -U
         const NO_OWNER_LISTED =          1 << 9;  // related to -g
         const NO_GROUPS_LISTED =         1 << 10; // -G --no-group
-        const HUMAN_READABLE_SIZES =     1 << 11; // -h --human-readable
-        const SI_SIZES =                 1 << 12; // --si
         const PRINT_INODE_INDEXES =      1 << 13; // -i --inode
         const KB_BLOCKS =                1 << 14; // -k --kibibytes
         const GROUP_DIRS_FIRST =         1 << 15; // --group-directories-first
-        const DEREF_SYMLINKS =           1 << 16; // -L --dereference
-        const COMMA_SEP_LIST =           1 << 17; // -m
         const NUMERIC_IDS =              1 << 18; // -n --numeric-uid-gid
-        const LITERAL_NAMES =            1 << 19; // -N --literal
         const REVERSE_SORT =             1 << 20; // -r, --reverse
         const RECURSIVE =                1 << 21; // -R --recursive
         const PRINT_ALLOCATED_SIZE =     1 << 22; // -s --size
@@ -44,9 +137,7 @@ bitflags::bitflags! {
         const LIST_BY_LINES =            1 << 24; // -x
         const PRINT_SECURIT_CTXT =       1 << 25; // -Z --context
         const END_WITH_NUL =             1 << 26; // --zero
-        const ONE_FILE_PER_LINE =        1 << 27; // -1
         const HIDE_CONTROL_CHARS =       1 << 28; // -q --hide-control-chars --show-control-chars
-        const QUOTE_ENTRIES =            1 << 29; // -Q --quote-name
         const DIRECTORIES_FIRST =        1 << 30;
 
         /// refers to the `-c` option
@@ -55,32 +146,61 @@ bitflags::bitflags! {
     }
 }
 
-pub(crate) fn parse_arguments<O: io::Write>(width: u16, out: &mut O) -> Result<LsConfig> {
-    let mut args = Parser::from_env()?;
+/// Parses the arguments, `personality` picks the defaults they don't change
+pub(crate) fn parse_arguments<O: io::Write>(
+    personality: Personality,
+    width: u16,
+    tty: bool,
+    out: &mut O,
+) -> Result<LsConfig> {
+    let raw = Args::from_env();
+    let mut args = raw.parser()?;
+    let program = personality.name();
+
+    let mut sort_order = None;
+    let mut explicit_time = false;
+    let mut time_style = None;
+    let mut deref = None;
+    let mut block_size = None;
+    let mut format = None;
+    let mut quoting = None;
+    let mut hide_control_chars = None;
 
     let mut settings = LsConfig {
+        personality,
         flags: LsFlags::empty(),
         order: SortOrder::Name,
+        time: TimeField::Modification,
         time_ty: TimeStampType::Locale,
         quoting: QuotingStyle::Literal,
         indicator: IndicatorStyle::None,
-        deref: Dereference::None,
-        color: When::Always,
-        hyperlink_file_names: When::Always,
-        classify_files: When::Always,
-        dir: None,
-        blk_size: DEFAULT_BLOCK_SIZE,
-        format: Formatting::Horizontal, // default seems to be horizontal
+        deref: Dereference::Never,
+        color: When::Never,
+        hyperlink_file_names: When::Never,
+        files: Vec::new(),
+        block_size: (HumanOpts::EXACT, DEFAULT_BLOCK_SIZE),
+        file_size: (HumanOpts::EXACT, 1),
+        tab_size: DEFAULT_TAB_SIZE,
+        ignore: Vec::new(),
+        hide: Vec::new(),
+        format: match personality {
+            Personality::Ls if tty => Formatting::Vertical,
+            Personality::Ls => Formatting::SingleCol,
+            Personality::Dir => Formatting::Vertical,
+            Personality::Vdir => Formatting::Long,
+        },
         width,
     };
 
     cli_with_args! {
-        args, "ls", out
+        args, #texts personality.version_text(), personality.help_text(), out
         Short('a') | Long("all") => {
+            settings.flags &= !LsFlags::IGNORE_DOTS_EXCEPT_DIRS;
             settings.flags |= LsFlags::NOT_IGNORE_DOTS;
         }
 
         Short('A') | Long("almost-all") => {
+            settings.flags &= !LsFlags::NOT_IGNORE_DOTS;
             settings.flags |= LsFlags::IGNORE_DOTS_EXCEPT_DIRS;
         }
 
@@ -89,15 +209,18 @@ pub(crate) fn parse_arguments<O: io::Write>(width: u16, out: &mut O) -> Result<L
         }
 
         Short('b') | Long("escape") => {
-            settings.flags |= LsFlags::C_STYLE_ESCAPED;
+            quoting = Some(QuotingStyle::Escape);
         }
 
         Long("block-size") => {
-            if let Some(_arg) = args.value() {
-                // do the block-size
-            } else {
-                needs_an_argument();
-            }
+            let Some(arg) = args.value() else {
+                needs_an_argument(program, "--block-size");
+            };
+
+            block_size = match human::parse_block_size(&arg) {
+                Err(err) => invalid_block_size(program, &arg, err),
+                Ok(size) => Some(size),
+            };
         }
 
         Short('B') | Long("ignore-backups") => {
@@ -105,22 +228,16 @@ pub(crate) fn parse_arguments<O: io::Write>(width: u16, out: &mut O) -> Result<L
         }
 
         Short('c') => {
-            settings.flags |= LsFlags::LOWERCASE_C;
+            settings.time = TimeField::Change;
+            explicit_time = true;
         }
 
         Short('C') => {
-            settings.flags |= LsFlags::LIST_BY_COLUMNS;
+            format = Some(Formatting::Vertical);
         }
 
         Long("color") => {
-            if let Some(value) = args.value() {
-                match When::from_bytes(&value) {
-                    None => invalid_argument(),
-                    Some(color) => settings.color = color,
-                }
-            } else {
-                needs_an_argument()
-            }
+            settings.color = optional_when(&mut args, program, "color");
         }
 
         Short('d') | Long("directory") => settings.flags |= LsFlags::LIST_DIRECTORIES
@@ -128,53 +245,42 @@ pub(crate) fn parse_arguments<O: io::Write>(width: u16, out: &mut O) -> Result<L
         Short('D') | Long("dired") => settings.flags |= LsFlags::EMACS_DIRED_MODE
 
         Short('f') => {
+            sort_order = Some(SortOrder::None);
             settings.flags &= !LsFlags::SORT_ENTRIES;
             settings.flags |= LsFlags::NOT_IGNORE_DOTS;
             settings.flags |= LsFlags::DIRECTORIES_FIRST;
             settings.color = When::Never;
         }
 
-        Short('F') | Long("classify") => {
-            settings.classify_files = match args.value() {
-                None => needs_an_argument(),
-                Some(val) => match When::from_bytes(&val) {
-                    None => invalid_argument(),
-                    Some(str) => str,
-                },
-            };
+        Short('F') => {
+            settings.indicator = IndicatorStyle::Classify;
         }
 
-        Long("file-type") => {
-            settings.classify_files = match args.value() {
-                None => needs_an_argument(),
-                Some(val) => match When::from_bytes(&val) {
-                    None => invalid_argument(),
-                    Some(str) => str,
-                },
-            };
+        Long("classify") => {
+            if optional_when(&mut args, program, "classify").enabled(tty) {
+                settings.indicator = IndicatorStyle::Classify;
+            }
+        }
 
-            // this should make so '*' doesn't appear
+        Long("file-type") => {
+            settings.indicator = IndicatorStyle::FileType;
         }
 
         Long("format") => {
-            settings.format = match args.value() {
-                None => needs_an_argument(),
-                Some(val) => match Formatting::from_bytes(&val) {
-                    None => invalid_argument(),
-
-                    Some(x) => x,
-                },
+            format = match args.value() {
+                None => needs_an_argument(program, "--format"),
+                Some(val) => Some(argmatch(program, "format", &val, Formatting::NAMES)),
             };
         }
 
         Long("full-time") => {
-            settings.format = Formatting::Long;
-            settings.time_ty = TimeStampType::FullIso;
+            format = Some(Formatting::Long);
+            time_style = Some("full-iso".to_owned());
         }
 
         Short('g') => {
             settings.flags |= LsFlags::NO_OWNER_LISTED;
-            settings.format = Formatting::Long;
+            format = Some(Formatting::Long);
         }
 
         Long("group-directories-first") => {
@@ -186,45 +292,37 @@ pub(crate) fn parse_arguments<O: io::Write>(width: u16, out: &mut O) -> Result<L
         }
 
         Short('h') | Long("human-readable") => {
-            settings.flags |= LsFlags::HUMAN_READABLE_SIZES;
+            block_size = Some((HumanOpts::HUMAN, 1));
         }
 
         Long("si") => {
-            settings.flags |= LsFlags::SI_SIZES;
+            block_size = Some((HumanOpts::SI, 1));
         }
 
         Short('H') | Long("dereference-command-line") => {
-            // not sure how to handle this
-            // requires some testing.
+            deref = Some(Dereference::CommandLineArguments);
         }
 
-        Long("derefence-command-line-symlinks") => {
-            // above
+        Long("dereference-command-line-symlink-to-dir") => {
+            deref = Some(Dereference::CommandLineSymlinkToDir);
         }
 
         Long("hide") => {
-            todo!("--hide")
+            let Some(pattern) = args.value() else {
+                needs_an_argument(program, "--hide");
+            };
+
+            settings.hide.push(pattern);
         }
 
         Long("hyperlink") => {
-            settings.hyperlink_file_names = match args.value() {
-                None => needs_an_argument(),
-                Some(val) => match When::from_bytes(&val) {
-                    None => invalid_argument(),
-
-                    Some(x) => x,
-                },
-            };
+            settings.hyperlink_file_names = optional_when(&mut args, program, "hyperlink");
         }
 
         Long("indicator-style") => {
             settings.indicator = match args.value() {
-                None => needs_an_argument(),
-                Some(val) => match IndicatorStyle::from_bytes(&val) {
-                    None => invalid_argument(),
-
-                    Some(x) => x,
-                },
+                None => needs_an_argument(program, "--indicator-style"),
+                Some(val) => argmatch(program, "indicator-style", &val, IndicatorStyle::NAMES),
             };
         }
 
@@ -232,34 +330,50 @@ pub(crate) fn parse_arguments<O: io::Write>(width: u16, out: &mut O) -> Result<L
             settings.flags |= LsFlags::PRINT_INODE_INDEXES;
         }
 
-        Short('I') | Long("ignore") => {
-            // todo
-            todo!()
+        Short('I') => {
+            let Some(pattern) = raw.value(&mut args, 'I') else {
+                needs_an_argument(program, "-I");
+            };
+
+            settings.ignore.push(pattern);
+        }
+
+        Long("ignore") => {
+            let Some(pattern) = raw.value(&mut args, 'I') else {
+                needs_an_argument(program, "--ignore");
+            };
+
+            settings.ignore.push(pattern);
+        }
+
+        Short('k') | Long("kibibytes") => {
+            settings.flags |= LsFlags::KB_BLOCKS;
         }
 
         Short('l') => {
-            settings.format = Formatting::Long;
+            format = Some(Formatting::Long);
         }
 
         Short('L') | Long("dereference") => {
-            settings.flags |= LsFlags::DEREF_SYMLINKS;
+            deref = Some(Dereference::Always);
         }
 
         Short('m') => {
-            settings.flags |= LsFlags::COMMA_SEP_LIST;
+            format = Some(Formatting::Commas);
         }
 
         Short('n') | Long("numeric-uid-gid") => {
             settings.flags |= LsFlags::NUMERIC_IDS;
+            format = Some(Formatting::Long);
         }
 
         Short('N') | Long("literal") => {
-            settings.flags |= LsFlags::LITERAL_NAMES;
+            quoting = Some(QuotingStyle::Literal);
         }
 
         Short('o') => {
             settings.flags |= LsFlags::NO_GROUPS_LISTED;
-            settings.format = Formatting::Long;
+            format = Some(Formatting::Long);
         }
 
         Short('p') => {
@@ -267,33 +381,22 @@ pub(crate) fn parse_arguments<O: io::Write>(width: u16, out: &mut O) -> Result<L
         }
 
         Short('q') | Long("hide-control-chars") => {
-            settings.flags |= LsFlags::HIDE_CONTROL_CHARS;
+            hide_control_chars = Some(true);
         }
 
         Long("show-control-chars") => {
-            settings.flags &= !LsFlags::HIDE_CONTROL_CHARS;
+            hide_control_chars = Some(false);
         }
 
         Short('Q') | Long("quote-name") => {
-            settings.flags |= LsFlags::QUOTE_ENTRIES;
+            quoting = Some(QuotingStyle::C);
         }
 
         Long("quoting-style") => {
-            settings.quoting = match args.value() {
-                None => needs_an_argument(),
-
-                Some(val) => match val.as_str() {
-                    "literal" => QuotingStyle::Literal,
-                    "locale" => QuotingStyle::Locale,
-                    "shell" => QuotingStyle::Shell,
-                    "shell-always" => QuotingStyle::ShellAlways,
-                    "shell-escape" => QuotingStyle::ShellEscape,
-                    "shell-escape-always" => QuotingStyle::ShellEscapeAlways,
-                    "c" => QuotingStyle::C,
-
-                    _ => invalid_argument(),
-                },
-            }
+            quoting = match args.value() {
+                None => needs_an_argument(program, "--quoting-style"),
+                Some(val) => Some(argmatch(program, "quoting-style", &val, QuotingStyle::NAMES)),
+            };
         }
 
         Short('r') | Long("reverse") => {
@@ -309,134 +412,246 @@ pub(crate) fn parse_arguments<O: io::Write>(width: u16, out: &mut O) -> Result<L
         }
 
         Short('S') => {
-            settings.order = SortOrder::Size;
+            sort_order = Some(SortOrder::Size);
         }
 
         Long("sort") => {
-            settings.order = if let Some(val) = args.value() {
-                if let Some(ret) = SortOrder::from_bytes(val.as_str()) {
-                    ret
-                } else {
-                    invalid_argument()
-                }
-            } else {
-                needs_an_argument();
-            }
+            sort_order = match args.value() {
+                None => needs_an_argument(program, "--sort"),
+                Some(val) => Some(argmatch(program, "sort", &val, SortOrder::NAMES)),
+            };
         }
 
         Long("time") => {
-            // todo
+            settings.time = match args.value() {
+                None => needs_an_argument(program, "--time"),
+                Some(val) => argmatch(program, "time", &val, TimeField::NAMES),
+            };
+            explicit_time = true;
         }
 
         Long("time-style") => {
-            // todo
+            time_style = match args.value() {
+                None => needs_an_argument(program, "--time-style"),
+                Some(val) => Some(val),
+            };
         }
 
         Short('t') => {
-            // todo
+            sort_order = Some(SortOrder::Time);
         }
 
-        Short('T') | Long("tabsize") => {
-            // todo
+        Short('T') => {
+            settings.tab_size = parse_tab_size(program, raw.value(&mut args, 'T'), "-T");
         }
 
-        Short('u') => {
-            match (&settings.format, &settings.order) {
-                (Formatting::Long, SortOrder::AccessTime) => {
-                    // i don't know
-                }
-
-                (Formatting::Long, _) => {
-                    settings.order = SortOrder::Name;
-
-                    // "show access time"
-                }
+        Long("tabsize") => {
+            settings.tab_size = parse_tab_size(program, raw.value(&mut args, 'T'), "--tabsize");
+        }
 
-                _ => {
-                    settings.order = SortOrder::AccessTime;
-                }
-            }
+        Short('u') => {
+            settings.time = TimeField::Access;
+            explicit_time = true;
         }
 
         Short('U') => {
-            settings.order = SortOrder::None;
+            sort_order = Some(SortOrder::None);
             settings.flags |= LsFlags::DIRECTORIES_FIRST;
         }
 
         Short('v') => {
-            // what does "natural sort of version numbers" mean...
+            sort_order = Some(SortOrder::Version);
         }
 
-        Short('w') | Long("width") => {
-            // limit width via another option...
+        Short('w') => {
+            settings.width = parse_width(program, raw.value(&mut args, 'w'), "-w");
+        }
+
+        Long("width") => {
+            settings.width = parse_width(program, raw.value(&mut args, 'w'), "--width");
         }
 
         Short('x') => {
-            // needs a new format mode...
+            format = Some(Formatting::Across);
         }
 
         Short('X') => {
-            settings.order = SortOrder::Extension;
+            sort_order = Some(SortOrder::Extension);
         }
 
         Short('Z') | Long("context") => {
-            // i need to learn what is a security context..
+            settings.flags |= LsFlags::PRINT_SECURIT_CTXT;
         }
 
         Long("zero") => {
             settings.flags |= LsFlags::END_WITH_NUL;
+            quoting = Some(QuotingStyle::Literal);
+            hide_control_chars = Some(false);
+            settings.color = When::Never;
+
+            if format != Some(Formatting::Long) {
+                format = Some(Formatting::SingleCol);
+            }
         }
 
         Short('1') => {
-            settings.flags |= LsFlags::ONE_FILE_PER_LINE;
+            // only a long format asked for stays
+            if format != Some(Formatting::Long) {
+                format = Some(Formatting::SingleCol);
+            }
         }
 
-        _ => {
-            // todo!
+        Value(file) => {
+            settings.files.push(file.into_owned());
+        }
+
+        Stdio => {
+            settings.files.push("-".into());
+        }
+
+        Long(option) => {
+            eprintln!("{program}: unrecognized option '--{option}'");
+            eprintln!("Try '{program} --help' for more information.");
+            exit(2)
+        }
+
+        Short(option) => {
+            eprintln!("{program}: invalid option -- '{option}'");
+            eprintln!("Try '{program} --help' for more information.");
+            exit(2)
         }
     }
 
+    settings.format = format.unwrap_or(settings.format);
+
+    // sorting by the time shown in long listings has to be asked for with -t
+    settings.order =
+        sort_order.unwrap_or(if explicit_time && settings.format != Formatting::Long {
+            SortOrder::Time
+        } else {
+            SortOrder::Name
+        });
+
+    // symlinks to directories given as arguments are listed as directories,
+    // unless the listing would show the symlinks themselves
+    settings.deref = deref.unwrap_or(
+        if settings.flags.contains(LsFlags::LIST_DIRECTORIES)
+            || settings.indicator == IndicatorStyle::Classify
+            || settings.format == Formatting::Long
+        {
+            Dereference::Never
+        } else {
+            Dereference::CommandLineSymlinkToDir
+        },
+    );
+
+    match block_size {
+        Some(block_size) => {
+            settings.block_size = block_size;
+            settings.file_size = block_size;
+        }
+        None => {
+            let ls_block_size = env::var("LS_BLOCK_SIZE").ok();
+            let scale_files = ls_block_size.is_some() || env::var_os("BLOCK_SIZE").is_some();
+
+            settings.block_size = env_block_size(ls_block_size);
+            if scale_files {
+                settings.file_size = settings.block_size;
+            }
+
+            // -k only overrides the environment
+            if settings.flags.contains(LsFlags::KB_BLOCKS) {
+                settings.block_size = (HumanOpts::EXACT, DEFAULT_BLOCK_SIZE);
+            }
+        }
+    }
+
+    settings.quoting =
+        quoting
+            .or_else(|| env_quoting_style(program))
+            .unwrap_or(match personality {
+                Personality::Ls if tty => QuotingStyle::ShellEscape,
+                Personality::Ls => QuotingStyle::Literal,
+                Personality::Dir | Personality::Vdir => QuotingStyle::Escape,
+            });
+
+    // dir and vdir show them wherever the output goes
+    if hide_control_chars.unwrap_or(tty && personality == Personality::Ls) {
+        settings.flags |= LsFlags::HIDE_CONTROL_CHARS;
+    }
+
+    if settings.format == Formatting::Long {
+        let style = time_style
+            .or_else(|| env::var("TIME_STYLE").ok())
+            .unwrap_or_else(|| "locale".to_owned());
+
+        settings.time_ty = match TimeStampType::from_style(&style) {
+            None => invalid_time_style(program, &style),
+            Some(time_ty) => time_ty,
+        };
+    }
+
+    // some terminals can't handle tabs mixed with colors
+    if settings.color.enabled(tty) {
+        settings.tab_size = 0;
+    }
+
     Ok(settings)
 }
 
 pub(crate) struct LsConfig {
+    // name ls was called by
+    pub(crate) personality: Personality,
+
     // order by which the entries will be sorted.
-    order: SortOrder,
+    pub(crate) order: SortOrder,
+
+    // timestamp shown in long listings and sorted by
+    pub(crate) time: TimeField,
 
-    // time of timestamp used by ls
-    time_ty: TimeStampType,
+    // style of timestamp used by ls
+    pub(crate) time_ty: TimeStampType,
 
     // settings that could be contained in bitflags.
-    flags: LsFlags,
+    pub(crate) flags: LsFlags,
 
     // quoting style for names
-    quoting: QuotingStyle,
+    pub(crate) quoting: QuotingStyle,
 
     // indicator style to append to entry names.
-    indicator: IndicatorStyle,
+    pub(crate) indicator: IndicatorStyle,
 
     // specifies how and which symlinks
     // should be dereferenced
-    deref: Dereference,
+    pub(crate) deref: Dereference,
 
     // related to --color.
-    color: When,
+    pub(crate) color: When,
 
     // related to --hyperlink
-    hyperlink_file_names: When,
+    pub(crate) hyperlink_file_names: When,
+
+    // files and directories to list.
+    pub(crate) files: Vec<String>,
 
-    // related to --classify and -F
-    classify_files: When,
+    // scaling of block counts, for -s and totals
+    pub(crate) block_size: (HumanOpts, u64),
 
-    // directory to search through.
-    dir: Option<String>,
+    // scaling of file sizes in long listings
+    pub(crate) file_size: (HumanOpts, u64),
 
-    // block size
-    blk_size: usize,
+    // patterns of names not to list, from -I
+    pub(crate) ignore: Vec<String>,
+
+    // patterns of names not to list without -a or -A, from --hide
+    pub(crate) hide: Vec<String>,
+
+    // distance between tab stops used for alignment, 0 disables tabs.
+    pub(crate) tab_size: usize,
 
     // formatting used
-    format: Formatting,
+    pub(crate) format: Formatting,
 
     // line width.
-    width: u16,
+    pub(crate) width: u16,
 }
@@ -0,0 +1,180 @@
+use std::{cmp::Ordering, os::unix::ffi::OsStrExt};
+
+use super::{
+    entry::Entry,
+    options::{SortOrder, TimeField},
+    settings::{LsConfig, LsFlags},
+};
+
+/// Sorts `entries` as `cfg` asks, `width` gives the columns a name takes up
+/// for `--sort=width`
+pub(crate) fn sort_entries(entries: &mut [Entry], cfg: &LsConfig, width: impl Fn(&Entry) -> usize) {
+    if cfg.order == SortOrder::None {
+        return;
+    }
+
+    let reverse = cfg.flags.contains(LsFlags::REVERSE_SORT);
+    let dirs_first = cfg.flags.contains(LsFlags::GROUP_DIRS_FIRST);
+
+    entries.sort_by(|a, b| {
+        if dirs_first {
+            let order = b.is_linked_dir().cmp(&a.is_linked_dir());
+            if order.is_ne() {
+                return order;
+            }
+        }
+
+        let (a, b) = if reverse { (b, a) } else { (a, b) };
+
+        compare(a, b, cfg.order, cfg.time, &width)
+    });
+}
+
+fn compare(
+    a: &Entry,
+    b: &Entry,
+    order: SortOrder,
+    time: TimeField,
+    width: impl Fn(&Entry) -> usize,
+) -> Ordering {
+    let name_a = a.name.as_bytes();
+    let name_b = b.name.as_bytes();
+
+    let primary = match order {
+        SortOrder::Size => b.size().cmp(&a.size()),
+        SortOrder::Time | SortOrder::AccessTime => sort_time(b, time).cmp(&sort_time(a, time)),
+        SortOrder::Extension => extension(name_a).cmp(extension(name_b)),
+        SortOrder::Width => width(a).cmp(&width(b)),
+        SortOrder::Version => filevercmp(name_a, name_b),
+        _ => Ordering::Equal,
+    };
+
+    primary.then_with(|| name_a.cmp(name_b))
+}
+
+/// Files whose timestamp is unknown sort as the oldest
+fn sort_time(entry: &Entry, time: TimeField) -> (i64, i64) {
+    match entry.time(time) {
+        Some(time) => time,
+        None if entry.meta.is_some() => (-1, -1),
+        None => (0, 0),
+    }
+}
+
+fn extension(name: &[u8]) -> &[u8] {
+    match name.iter().rposition(|&b| b == b'.') {
+        Some(dot) => &name[dot..],
+        None => b"",
+    }
+}
+
+/// Compares two file names the way GNU's `filevercmp` does,
+/// treating runs of digits as numbers and ignoring file suffixes first.
+pub(crate) fn filevercmp(a: &[u8], b: &[u8]) -> Ordering {
+    if a.is_empty() || b.is_empty() {
+        return (!a.is_empty()).cmp(&!b.is_empty());
+    }
+
+    if a[0] == b'.' {
+        if b[0] != b'.' {
+            return Ordering::Less;
+        }
+
+        for special in [&b"."[..], b".."] {
+            match (a == special, b == special) {
+                (true, true) => return Ordering::Equal,
+                (true, false) => return Ordering::Less,
+                (false, true) => return Ordering::Greater,
+                (false, false) => {}
+            }
+        }
+    } else if b[0] == b'.' {
+        return Ordering::Greater;
+    }
+
+    let a_prefix = prefix_len(a);
+    let b_prefix = prefix_len(b);
+
+    match verrevcmp(&a[..a_prefix], &b[..b_prefix]) {
+        Ordering::Equal if a_prefix != a.len() || b_prefix != b.len() => verrevcmp(a, b),
+        order => order,
+    }
+}
+
+/// Length of `name` without its longest suffix matching `(\.[A-Za-z~][A-Za-z0-9~]*)*$`,
+/// never treating the whole name as a suffix.
+fn prefix_len(name: &[u8]) -> usize {
+    let mut prefix = 0;
+    let mut i = 0;
+
+    while i < name.len() {
+        i += 1;
+        prefix = i;
+
+        while i + 1 < name.len()
+            && name[i] == b'.'
+            && (name[i + 1].is_ascii_alphabetic() || name[i + 1] == b'~')
+        {
+            i += 2;
+            while i < name.len() && (name[i].is_ascii_alphanumeric() || name[i] == b'~') {
+                i += 1;
+            }
+        }
+    }
+
+    prefix
+}
+
+fn order(name: &[u8], pos: usize) -> i32 {
+    match name.get(pos) {
+        None => 0,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(&c) if c.is_ascii_alphabetic() => c as i32,
+        Some(b'~') => -1,
+        Some(&c) => c as i32 + 256,
+    }
+}
+
+fn verrevcmp(a: &[u8], b: &[u8]) -> Ordering {
+    let (mut i, mut j) = (0, 0);
+    let is_digit = |name: &[u8], pos: usize| name.get(pos).is_some_and(u8::is_ascii_digit);
+
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !a[i].is_ascii_digit()) || (j < b.len() && !b[j].is_ascii_digit()) {
+            let (oa, ob) = (order(a, i), order(b, j));
+            if oa != ob {
+                return oa.cmp(&ob);
+            }
+            i += 1;
+            j += 1;
+        }
+
+        while a.get(i) == Some(&b'0') {
+            i += 1;
+        }
+        while b.get(j) == Some(&b'0') {
+            j += 1;
+        }
+
+        let mut first_diff = Ordering::Equal;
+        while is_digit(a, i) && is_digit(b, j) {
+            if first_diff.is_eq() {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+
+        if is_digit(a, i) {
+            return Ordering::Greater;
+        }
+        if is_digit(b, j) {
+            return Ordering::Less;
+        }
+        if first_diff.is_ne() {
+            return first_diff;
+        }
+    }
+
+    Ordering::Equal
+}
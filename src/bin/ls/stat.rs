@@ -0,0 +1,86 @@
+use std::{ffi::CString, io, mem::MaybeUninit, os::unix::ffi::OsStrExt, path::Path};
+
+use super::entry::{S_IFDIR, S_IFLNK, S_IFMT, S_IFREG};
+
+pub(crate) use libc::{
+    STATX_ATIME, STATX_BLOCKS, STATX_BTIME, STATX_CTIME, STATX_GID, STATX_INO, STATX_MODE,
+    STATX_MTIME, STATX_NLINK, STATX_SIZE, STATX_TYPE, STATX_UID,
+};
+
+/// What `statx` told about a file, fields that weren't asked for may be 0
+#[derive(Clone)]
+pub(crate) struct Stat {
+    pub(crate) mode: u32,
+    pub(crate) ino: u64,
+    pub(crate) nlink: u64,
+    pub(crate) uid: u32,
+    pub(crate) gid: u32,
+    pub(crate) size: u64,
+    pub(crate) blocks: u64,
+    /// major and minor number of device files
+    pub(crate) rdev: (u32, u32),
+    pub(crate) atime: (i64, i64),
+    pub(crate) mtime: (i64, i64),
+    pub(crate) ctime: (i64, i64),
+    /// `None` where the file system doesn't keep it
+    pub(crate) btime: Option<(i64, i64)>,
+}
+
+impl Stat {
+    #[inline]
+    pub(crate) fn file_type(&self) -> u32 {
+        self.mode & S_IFMT
+    }
+
+    #[inline]
+    pub(crate) fn is_dir(&self) -> bool {
+        self.file_type() == S_IFDIR
+    }
+
+    #[inline]
+    pub(crate) fn is_file(&self) -> bool {
+        self.file_type() == S_IFREG
+    }
+
+    #[inline]
+    pub(crate) fn is_symlink(&self) -> bool {
+        self.file_type() == S_IFLNK
+    }
+}
+
+/// `stat`s `path` asking only for the fields in `mask`,
+/// symlinks are only followed with `follow`.
+pub(crate) fn statx(path: &Path, follow: bool, mask: u32) -> io::Result<Stat> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+
+    let mut flags = libc::AT_NO_AUTOMOUNT;
+    if !follow {
+        flags |= libc::AT_SYMLINK_NOFOLLOW;
+    }
+
+    let mut buf = MaybeUninit::<libc::statx>::uninit();
+    // SAFETY: `path` is NUL terminated and `buf` has room for a `statx`
+    let ret = unsafe { libc::statx(libc::AT_FDCWD, path.as_ptr(), flags, mask, buf.as_mut_ptr()) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: `statx` succeeded, so it filled in `buf`
+    let stx = unsafe { buf.assume_init() };
+    let time = |time: libc::statx_timestamp| (time.tv_sec, time.tv_nsec as i64);
+
+    Ok(Stat {
+        mode: stx.stx_mode as u32,
+        ino: stx.stx_ino,
+        nlink: stx.stx_nlink as u64,
+        uid: stx.stx_uid,
+        gid: stx.stx_gid,
+        size: stx.stx_size,
+        blocks: stx.stx_blocks,
+        rdev: (stx.stx_rdev_major, stx.stx_rdev_minor),
+        atime: time(stx.stx_atime),
+        mtime: time(stx.stx_mtime),
+        ctime: time(stx.stx_ctime),
+        btime: (stx.stx_mask & STATX_BTIME != 0).then(|| time(stx.stx_btime)),
+    })
+}
@@ -0,0 +1,66 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+/// Names of users and groups, each database is read once
+/// on the first lookup and kept for the files after it.
+#[derive(Default)]
+pub(crate) struct Names {
+    users: Option<HashMap<u32, Vec<u8>>>,
+    groups: Option<HashMap<u32, Vec<u8>>>,
+}
+
+impl Names {
+    pub(crate) fn user_name(&mut self, uid: u32) -> Option<&[u8]> {
+        self.users
+            .get_or_insert_with(|| read_names("/etc/passwd"))
+            .get(&uid)
+            .map(Vec::as_slice)
+    }
+
+    pub(crate) fn group_name(&mut self, gid: u32) -> Option<&[u8]> {
+        self.groups
+            .get_or_insert_with(|| read_names("/etc/group"))
+            .get(&gid)
+            .map(Vec::as_slice)
+    }
+}
+
+/// Reads the names by id from `/etc/passwd` or `/etc/group`, which both start
+/// with `name:password:id`. Names are kept as bytes, they needn't be UTF-8.
+fn read_names(path: &str) -> HashMap<u32, Vec<u8>> {
+    let mut names = HashMap::new();
+    let Ok(file) = File::open(path) else {
+        return names;
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+
+    while reader
+        .read_until(b'\n', &mut line)
+        .is_ok_and(|len| len != 0)
+    {
+        let mut fields = line
+            .strip_suffix(b"\n")
+            .unwrap_or(&line)
+            .splitn(4, |&b| b == b':');
+
+        if let (Some(name), Some(_), Some(id)) = (fields.next(), fields.next(), fields.next())
+            && let Some(id) = parse_id(id)
+        {
+            // like getpwuid and getgrgid, the first entry with an id wins
+            names.entry(id).or_insert_with(|| name.to_vec());
+        }
+
+        line.clear();
+    }
+
+    names
+}
+
+fn parse_id(id: &[u8]) -> Option<u32> {
+    str::from_utf8(id).ok()?.parse().ok()
+}
@@ -0,0 +1,86 @@
+use std::{ffi::CString, io, os::unix::ffi::OsStrExt, path::Path, ptr};
+
+pub(crate) const SELINUX: &[u8] = b"security.selinux";
+pub(crate) const CAPABILITY: &[u8] = b"security.capability";
+pub(crate) const POSIX_ACL_ACCESS: &[u8] = b"system.posix_acl_access";
+pub(crate) const POSIX_ACL_DEFAULT: &[u8] = b"system.posix_acl_default";
+
+// tags of the entries that only mirror the permission bits
+const ACL_USER_OBJ: u16 = 0x01;
+const ACL_GROUP_OBJ: u16 = 0x04;
+const ACL_OTHER: u16 = 0x20;
+
+/// Names of the extended attributes of `path`, separated by NULs,
+/// symlinks are only followed with `follow`.
+pub(crate) fn list(path: &Path, follow: bool) -> io::Result<Vec<u8>> {
+    let path = c_path(path)?;
+
+    // SAFETY: `path` is NUL terminated and `read` hands us either a null
+    // buffer with a length of 0 or one of `len` writable bytes
+    read(|buf, len| unsafe {
+        if follow {
+            libc::listxattr(path.as_ptr(), buf, len)
+        } else {
+            libc::llistxattr(path.as_ptr(), buf, len)
+        }
+    })
+}
+
+/// Value of the extended attribute `name` of `path`
+pub(crate) fn get(path: &Path, name: &[u8], follow: bool) -> io::Result<Vec<u8>> {
+    let path = c_path(path)?;
+    let name = CString::new(name)?;
+
+    // SAFETY: `path` and `name` are NUL terminated, and the buffer is as in `list`
+    read(|buf, len| unsafe {
+        if follow {
+            libc::getxattr(path.as_ptr(), name.as_ptr(), buf.cast(), len)
+        } else {
+            libc::lgetxattr(path.as_ptr(), name.as_ptr(), buf.cast(), len)
+        }
+    })
+}
+
+/// Whether `list` contains the attribute `name`
+pub(crate) fn contains(list: &[u8], name: &[u8]) -> bool {
+    list.split(|&byte| byte == 0).any(|entry| entry == name)
+}
+
+/// Whether a `system.posix_acl_access` value grants anything
+/// the permission bits can't express.
+pub(crate) fn is_nontrivial_acl(acl: &[u8]) -> bool {
+    // a 4 byte version, then entries of a 2 byte tag, 2 byte permissions and 4 byte id
+    acl.get(4..).unwrap_or_default().chunks(8).any(|entry| {
+        let tag = u16::from_le_bytes([entry[0], entry.get(1).copied().unwrap_or(0)]);
+        !matches!(tag, ACL_USER_OBJ | ACL_GROUP_OBJ | ACL_OTHER)
+    })
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    Ok(CString::new(path.as_os_str().as_bytes())?)
+}
+
+/// Calls `op` with a buffer big enough for what it returns, `op` gets
+/// a null buffer and a length of 0 when only asked for the size.
+fn read(op: impl Fn(*mut libc::c_char, usize) -> isize) -> io::Result<Vec<u8>> {
+    loop {
+        let size = op(ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut buf = vec![0u8; size as usize];
+        let len = op(buf.as_mut_ptr().cast(), buf.len());
+
+        if len >= 0 {
+            buf.truncate(len as usize);
+            return Ok(buf);
+        }
+
+        let err = io::Error::last_os_error();
+        // the attribute grew in between
+        if err.raw_os_error() != Some(libc::ERANGE) {
+            return Err(err);
+        }
+    }
+}
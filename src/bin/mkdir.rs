@@ -1,24 +1,244 @@
-use std::io::stdout;
+use std::{
+    fs::{self, Permissions},
+    io::{self, Write, stdout},
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    process::exit,
+};
 
-use puppyutils::{Result, cli, get_umask};
-use xenia::{Mode, mkdir};
+use puppyutils::{
+    Result,
+    args::Args,
+    cli_with_args, error_message, get_umask, missing_argument,
+    mode::{ModeChange, apply_mode},
+};
+use xenia::{Errno, Mode, mkdir};
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy)]
+    struct Flags: u8 {
+        const PARENTS = 1 << 0;
+        const VERBOSE = 1 << 1;
+    }
+}
+
+struct Options {
+    flags: Flags,
+    umask: Mode,
+    /// The mode from `-m`
+    mode: Option<ModeChange>,
+}
+
+/// Exits like `getopt` does when `option` is given without its argument
+fn needs_an_argument(option: &str) -> ! {
+    missing_argument("mkdir", option);
+    exit(1)
+}
+
+/// Why making a directory failed, the other operands are
+/// still attempted after anything but write errors
+enum MkdirError {
+    Create(String, io::Error),
+    SetMode(String, io::Error),
+    Write(io::Error),
+}
+
+fn chmod(path: &str, mode: u32) -> io::Result<()> {
+    fs::set_permissions(path, Permissions::from_mode(mode))
+}
+
+/// Creates `dir`, an `EEXIST` is returned for the caller to decide on
+fn create<W: Write>(
+    dir: &str,
+    mode: Mode,
+    options: &Options,
+    out: &mut W,
+) -> Result<(), MkdirError> {
+    if let Err(err) = mkdir(dir, mode) {
+        let err = io::Error::from_raw_os_error(err.raw_os_error());
+        return Err(MkdirError::Create(dir.to_owned(), err));
+    }
+
+    if options.flags.contains(Flags::VERBOSE) {
+        writeln!(out, "mkdir: created directory '{dir}'").map_err(MkdirError::Write)?;
+    }
+
+    Ok(())
+}
+
+fn exists(err: &MkdirError) -> bool {
+    let exist = Some(Errno::EXIST.raw_os_error());
+
+    matches!(err, MkdirError::Create(_, err) if err.raw_os_error() == exist)
+}
+
+/// Creates the missing ancestors of `dir` like GNU `mkdir -p`, with at
+/// least `u+wx` so that their own children can be created in them
+fn create_parents<W: Write>(dir: &str, options: &Options, out: &mut W) -> Result<(), MkdirError> {
+    let umask = options.umask.bits() & 0o777;
+    let ends = dir
+        .match_indices('/')
+        .map(|(index, _)| index)
+        // neither the root, repeated slashes nor trailing ones end a parent
+        .filter(|&index| {
+            index > 0
+                && dir.as_bytes()[index - 1] != b'/'
+                && dir[index..].bytes().any(|byte| byte != b'/')
+        });
+
+    for end in ends {
+        let parent = &dir[..end];
+
+        match create(parent, Mode::from_bits_retain(0o777), options, out) {
+            Ok(()) if umask & 0o300 != 0 => chmod(parent, (0o777 & !umask) | 0o300)
+                .map_err(|err| MkdirError::SetMode(parent.to_owned(), err))?,
+            Ok(()) => {}
+            Err(err) if exists(&err) => {
+                if !fs::metadata(parent).is_ok_and(|meta| meta.is_dir()) {
+                    let err = io::Error::from_raw_os_error(Errno::NOTDIR.raw_os_error());
+                    return Err(MkdirError::Create(parent.to_owned(), err));
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+/// The mode `dir` is created with for `-m`. Like GNU, a directory that
+/// gets special bits isn't writable by the group and others until it has them.
+fn creation_mode(change: ModeChange) -> u32 {
+    let mode = change.mode.bits();
+
+    if (change.changed.bits() & 0o6000) | (mode & 0o1000) != 0 {
+        mode & 0o1755
+    } else {
+        mode & 0o1777
+    }
+}
+
+/// Gives the new directory `dir` the mode from `-m`, which `mkdir` alone
+/// can't do for the set-ID bits. Like GNU, the umask never takes from the
+/// mode, and the created mode is only changed if it differs in the bits
+/// the mode sets or clears, so that the set-GID bit inherited from the
+/// parent stays unless the mode mentions it.
+fn set_mode(dir: &str, change: ModeChange) -> io::Result<()> {
+    let created = fs::metadata(dir)?.mode() & 0o7777;
+    let (mode, changed) = (change.mode.bits(), change.changed.bits());
+
+    // GNU sets the umask aside while creating it
+    let unmasked = (created & 0o6000) | creation_mode(change);
+
+    let wanted = if (unmasked ^ mode) & changed != 0 {
+        mode | (unmasked & !changed)
+    } else {
+        unmasked
+    };
+
+    if wanted != created {
+        chmod(dir, wanted)?;
+    }
+
+    Ok(())
+}
+
+fn make_dir<W: Write>(dir: &str, options: &Options, out: &mut W) -> Result<(), MkdirError> {
+    let parents = options.flags.contains(Flags::PARENTS);
+
+    if parents {
+        create_parents(dir, options, out)?;
+    }
+
+    let mode = match options.mode {
+        Some(change) => Mode::from_bits_retain(creation_mode(change)),
+        None => Mode::from_bits_retain(0o777),
+    };
+
+    match create(dir, mode, options, out) {
+        Ok(()) => {}
+        // existing directories are left as they are
+        Err(err)
+            if parents && exists(&err) && fs::metadata(dir).is_ok_and(|meta| meta.is_dir()) =>
+        {
+            return Ok(());
+        }
+        Err(err) => return Err(err),
+    }
+
+    if let Some(change) = options.mode {
+        set_mode(dir, change).map_err(|err| MkdirError::SetMode(dir.to_owned(), err))?;
+    }
+
+    Ok(())
+}
 
 pub fn main() -> Result {
     let mut stdout = stdout();
+    let raw = Args::from_env();
+    let mut args = raw.parser()?;
 
     let mut dirs = Vec::new();
+    let mut flags = Flags::empty();
+    let mut mode = None;
 
-    cli! {
-        "mkdir", stdout, #error
+    cli_with_args! {
+        args, "mkdir", stdout, #error
+        Short('p') | Long("parents") => flags |= Flags::PARENTS
+        Short('v') | Long("verbose") => flags |= Flags::VERBOSE
+        Short('m') => {
+            mode = Some(raw.value(&mut args, 'm').unwrap_or_else(|| needs_an_argument("-m")));
+        }
+        Long("mode") => {
+            mode = Some(raw.value(&mut args, 'm').unwrap_or_else(|| needs_an_argument("--mode")));
+        }
         Value(value) => {
             dirs.push(value.into_owned()); // FIXME: I am not a fan of all this allocation but we can't easily move out of the argument parser
         }
     };
 
-    let mode = Mode::from_bits_retain(0o777) & !get_umask();
+    if dirs.is_empty() {
+        eprintln!("mkdir: missing operand");
+        eprintln!("Try 'mkdir --help' for more information.");
+        exit(1);
+    }
+
+    let umask = get_umask();
+
+    let mode = mode.map(|spec| {
+        let Some(change) = apply_mode(&spec, Mode::from_bits_retain(0o777), umask, true) else {
+            eprintln!("mkdir: invalid mode '{spec}'");
+            exit(1);
+        };
+
+        change
+    });
+
+    let options = Options { flags, umask, mode };
+    let mut status = 0;
 
     for dir in dirs {
-        mkdir(dir, mode)?;
+        match make_dir(&dir, &options, &mut stdout) {
+            Ok(()) => {}
+            Err(MkdirError::Create(path, err)) => {
+                eprintln!(
+                    "mkdir: cannot create directory '{path}': {}",
+                    error_message(&err)
+                );
+                status = 1;
+            }
+            Err(MkdirError::SetMode(path, err)) => {
+                eprintln!(
+                    "mkdir: cannot set permissions of '{path}': {}",
+                    error_message(&err)
+                );
+                status = 1;
+            }
+            Err(MkdirError::Write(err)) => return Err(err.into()),
+        }
+    }
+
+    if status != 0 {
+        exit(status);
     }
 
     Ok(())
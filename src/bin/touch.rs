@@ -1,58 +1,269 @@
-use std::{ffi::CStr, io::stdout};
+use std::{
+    ffi::CStr,
+    fs,
+    io::{self, stdout},
+    os::{fd::AsFd, unix::fs::MetadataExt},
+    process::exit,
+};
 
-use puppyutils::{Result, cli};
+use puppyutils::{
+    Result, argmatch,
+    args::Args,
+    cli_with_args, error_message, missing_argument,
+    time::{
+        TimeZone,
+        parse::{parse_date, parse_posix_stamp},
+    },
+};
 use xenia::{
     AtFlags, ClockId, Errno, Mode, OFlags, Timespec, Timestamps, clock_gettime, open, stdio::cwd,
     utimensat,
 };
 
+bitflags::bitflags! {
+    #[derive(Clone, Copy, PartialEq)]
+    struct Flags: u8 {
+        const NO_CREATE      = 1 << 0;
+        const NO_DEREFERENCE = 1 << 1;
+        const NO_ACCESS      = 1 << 2;
+        const NO_MODIFY      = 1 << 3;
+    }
+}
+
+/// What `--time` can change, each on its own
+const TIMES: [(&str, Flags); 5] = [
+    ("atime", Flags::NO_MODIFY),
+    ("access", Flags::NO_MODIFY),
+    ("use", Flags::NO_MODIFY),
+    ("mtime", Flags::NO_ACCESS),
+    ("modify", Flags::NO_ACCESS),
+];
+
+/// The argument of `option`, exiting when there isn't one
+fn required(value: Option<String>, option: &str) -> String {
+    let Some(value) = value else {
+        missing_argument("touch", option);
+        exit(1);
+    };
+
+    value
+}
+
+fn invalid_date(date: &str) -> ! {
+    eprintln!("touch: invalid date format '{date}'");
+    exit(1);
+}
+
+fn io_error(errno: Errno) -> io::Error {
+    io::Error::from_raw_os_error(errno.raw_os_error())
+}
+
+/// The times of the file at `path`, for `-r`
+fn reference_times(path: &str, flags: Flags) -> Timestamps {
+    let meta = if flags.contains(Flags::NO_DEREFERENCE) {
+        fs::symlink_metadata(path)
+    } else {
+        fs::metadata(path)
+    };
+
+    match meta {
+        Ok(meta) => Timestamps {
+            last_access: Timespec {
+                tv_sec: meta.atime(),
+                tv_nsec: meta.atime_nsec(),
+            },
+            last_modification: Timespec {
+                tv_sec: meta.mtime(),
+                tv_nsec: meta.mtime_nsec(),
+            },
+        },
+        Err(err) => {
+            eprintln!(
+                "touch: failed to get attributes of '{path}': {}",
+                error_message(&err)
+            );
+            exit(1);
+        }
+    }
+}
+
+/// Sets the times of `file`, creating it if needed unless `-c` or `-h` say
+/// otherwise. "-" is standard output, which is never created.
+fn touch(file: &str, timestamps: &Timestamps, flags: Flags) -> Result<(), String> {
+    let result = if file == "-" {
+        utimensat::<_, &CStr>(stdout().as_fd(), None, timestamps, AtFlags::empty())
+    } else if flags.contains(Flags::NO_DEREFERENCE) {
+        utimensat(cwd(), file, timestamps, AtFlags::SYMLINK_NOFOLLOW)
+    } else {
+        utimensat(cwd(), file, timestamps, AtFlags::empty())
+    };
+
+    let errno = match result {
+        Ok(()) => return Ok(()),
+        Err(errno) => errno,
+    };
+
+    let missing = errno == Errno::NOENT;
+
+    if missing && flags.contains(Flags::NO_CREATE) {
+        return Ok(());
+    }
+
+    if !missing || file == "-" || flags.contains(Flags::NO_DEREFERENCE) {
+        return Err(format!(
+            "setting times of '{file}': {}",
+            error_message(&io_error(errno))
+        ));
+    }
+
+    let fd = open(
+        file,
+        OFlags::WRONLY | OFlags::CREAT | OFlags::NOCTTY | OFlags::NONBLOCK,
+        Mode::from_bits_retain(0o666),
+    )
+    .map_err(|errno| format!("cannot touch '{file}': {}", error_message(&io_error(errno))))?;
+
+    utimensat::<_, &CStr>(fd, None, timestamps, AtFlags::empty()).map_err(|errno| {
+        format!(
+            "setting times of '{file}': {}",
+            error_message(&io_error(errno))
+        )
+    })
+}
+
 pub fn main() -> Result {
     let mut stdout = stdout();
+    let raw = Args::from_env();
+    let mut args = raw.parser()?;
     let mut files = Vec::new();
 
-    let mut no_create = false;
-    let mut access_time = true;
-    let mut modification_time = true;
+    let mut flags = Flags::empty();
+    let mut reference = None;
+    let mut date = None;
+    let mut stamp = None;
 
-    cli! {
-        "touch", stdout, #error
-        Short('c') | Long("no-create") => no_create = true
-        Short('a') => modification_time = false
-        Short('m')=> access_time = false
+    cli_with_args! {
+        args, "touch", stdout, #error
+        Short('a') => flags |= Flags::NO_MODIFY
+        Short('c') | Long("no-create") => flags |= Flags::NO_CREATE
+        Short('f') => {}
+        Short('h') | Long("no-dereference") => flags |= Flags::NO_DEREFERENCE
+        Short('m') => flags |= Flags::NO_ACCESS
+        Short('d') => date = Some(required(raw.value(&mut args, 'd'), "-d"))
+        Long("date") => date = Some(required(raw.value(&mut args, 'd'), "--date"))
+        Short('r') => reference = Some(required(raw.value(&mut args, 'r'), "-r"))
+        Long("reference") => reference = Some(required(raw.value(&mut args, 'r'), "--reference"))
+        Short('t') => stamp = Some(required(raw.value(&mut args, 't'), "-t"))
+        Long("time") => {
+            let word = required(args.value(), "--time");
+            flags |= argmatch("touch", "time", &word, &TIMES);
+        }
         Value(value) => {
             files.push(value.into_owned());
         }
+        Stdio => files.push("-".to_owned())
     };
 
-    let now = if files.len() == 1 {
-        Timespec::NOW
-    } else {
-        clock_gettime(ClockId::Realtime)?
-    };
+    if files.is_empty() {
+        eprintln!("touch: missing file operand");
+        eprintln!("Try 'touch --help' for more information.");
+        exit(1);
+    }
 
-    #[rustfmt::skip]
-    let timestamps = Timestamps {
-        last_access: if access_time { now } else { Timespec::OMIT },
-        last_modification: if modification_time { now } else { Timespec::OMIT },
-    };
+    // `-a` and `-m` together change both times, like neither of them
+    if flags.contains(Flags::NO_ACCESS | Flags::NO_MODIFY) {
+        flags -= Flags::NO_ACCESS | Flags::NO_MODIFY;
+    }
 
-    for file in files {
-        match utimensat(cwd(), &file, &timestamps, AtFlags::empty()) {
-            Ok(_) => {}
-            Err(errno) => {
-                if no_create || errno != Errno::NOENT {
-                    return Err(errno.into());
+    if stamp.is_some() && (date.is_some() || reference.is_some()) {
+        eprintln!("touch: cannot specify times from more than one source");
+        exit(1);
+    }
+
+    let mut timestamps = match (reference, date, stamp) {
+        (_, _, Some(stamp)) => {
+            let now = clock_gettime(ClockId::Realtime)?;
+            let Some(seconds) = parse_posix_stamp(&stamp, now.tv_sec, &TimeZone::local()) else {
+                invalid_date(&stamp);
+            };
+
+            let time = Timespec {
+                tv_sec: seconds,
+                tv_nsec: 0,
+            };
+
+            Timestamps {
+                last_access: time,
+                last_modification: time,
+            }
+        }
+        // like GNU, a relative date is relative to the times of the reference
+        (reference, Some(date), _) => {
+            let base = match reference {
+                Some(reference) => reference_times(&reference, flags),
+                None => {
+                    let now = clock_gettime(ClockId::Realtime)?;
+
+                    Timestamps {
+                        last_access: now,
+                        last_modification: now,
+                    }
                 }
+            };
 
-                let fd = open(
-                    file,
-                    OFlags::WRONLY | OFlags::CREAT,
-                    Mode::from_bits_retain(0o666),
-                )?;
+            let tz = TimeZone::local();
+            let parse = |time: Timespec| {
+                let Some((seconds, nanos)) =
+                    parse_date(&date, (time.tv_sec, time.tv_nsec as u32), &tz)
+                else {
+                    invalid_date(&date);
+                };
 
-                utimensat::<_, &CStr>(fd, None, &timestamps, AtFlags::empty())?;
+                Timespec {
+                    tv_sec: seconds,
+                    tv_nsec: nanos.into(),
+                }
+            };
+
+            Timestamps {
+                last_access: parse(base.last_access),
+                last_modification: parse(base.last_modification),
+            }
+        }
+        (Some(reference), None, None) => reference_times(&reference, flags),
+        (None, None, None) => {
+            let now = if files.len() == 1 {
+                Timespec::NOW
+            } else {
+                clock_gettime(ClockId::Realtime)?
+            };
+
+            Timestamps {
+                last_access: now,
+                last_modification: now,
             }
         }
+    };
+
+    if flags.contains(Flags::NO_ACCESS) {
+        timestamps.last_access = Timespec::OMIT;
+    }
+
+    if flags.contains(Flags::NO_MODIFY) {
+        timestamps.last_modification = Timespec::OMIT;
+    }
+
+    let mut status = 0;
+
+    for file in files {
+        if let Err(message) = touch(&file, &timestamps, flags) {
+            eprintln!("touch: {message}");
+            status = 1;
+        }
+    }
+
+    if status != 0 {
+        exit(status);
     }
 
     Ok(())
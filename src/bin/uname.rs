@@ -1,4 +1,10 @@
-use std::io::{Write, stdout};
+use std::{
+    ffi::CStr,
+    fs,
+    io::{Write, stdout},
+    os::unix::ffi::OsStrExt,
+    slice,
+};
 
 use puppyutils::{Result, cli};
 use xenia::uname;
@@ -17,6 +23,182 @@ bitflags::bitflags! {
     }
 }
 
+/// The family `machine` belongs to, for `-i`
+fn hardware_platform(machine: &[u8]) -> Option<&'static [u8]> {
+    let platform: &[u8] = match machine {
+        b"i386" | b"i486" | b"i586" | b"i686" => b"i386",
+        b"x86_64" | b"amd64" => b"x86_64",
+        b"aarch64" | b"arm64" | b"armv8l" => b"aarch64",
+        b"aarch64_be" => b"aarch64_be",
+        b"riscv64" => b"riscv64",
+        b"riscv32" => b"riscv32",
+        b"ppc64le" => b"ppc64le",
+        b"ppc64" => b"ppc64",
+        b"ppc" => b"ppc",
+        b"s390x" => b"s390x",
+        b"s390" => b"s390",
+        b"loongarch64" => b"loongarch64",
+        b"mips64" => b"mips64",
+        b"mips" => b"mips",
+        b"sparc64" => b"sparc64",
+        b"alpha" => b"alpha",
+        b"m68k" => b"m68k",
+        _ if machine.starts_with(b"arm") => b"arm",
+        _ => return None,
+    };
+
+    Some(platform)
+}
+
+/// The processor type, for `-p`. That's the `AT_PLATFORM` the kernel gave
+/// us, which names the actual CPU on some architectures, or the ISA in
+/// `/proc/cpuinfo` for RISC-V, which has none. Otherwise it's the family.
+fn processor(machine: &[u8]) -> Option<Vec<u8>> {
+    // SAFETY: `AT_PLATFORM` is either 0 or points to a string on our stack
+    let platform = unsafe { libc::getauxval(libc::AT_PLATFORM) };
+
+    if platform != 0 {
+        // SAFETY: checked above
+        let platform = unsafe { CStr::from_ptr(platform as *const libc::c_char) }.to_bytes();
+
+        // 32-bit ARM only says `v7l` and the like
+        return match platform {
+            [b'v', digit, ..] if digit.is_ascii_digit() => Some([b"arm", platform].concat()),
+            _ => Some(platform.to_vec()),
+        };
+    }
+
+    if let Ok(cpuinfo) = fs::read_to_string("/proc/cpuinfo")
+        && let Some(isa) = cpuinfo.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "isa").then(|| value.trim())
+        })
+    {
+        if isa.starts_with("rv64") {
+            return Some(b"riscv64".to_vec());
+        }
+
+        if isa.starts_with("rv32") {
+            return Some(b"riscv32".to_vec());
+        }
+    }
+
+    hardware_platform(machine).map(<[u8]>::to_vec)
+}
+
+#[cfg(target_pointer_width = "64")]
+type ProgramHeader = libc::Elf64_Phdr;
+#[cfg(target_pointer_width = "32")]
+type ProgramHeader = libc::Elf32_Phdr;
+
+/// The dynamic loader we were started with, from our `PT_INTERP`.
+/// Static executables have none.
+fn interpreter() -> Option<&'static CStr> {
+    // SAFETY: these are plain values from the auxiliary vector
+    let (phdr, phnum) = unsafe {
+        (
+            libc::getauxval(libc::AT_PHDR) as usize,
+            libc::getauxval(libc::AT_PHNUM) as usize,
+        )
+    };
+
+    if phdr == 0 {
+        return None;
+    }
+
+    // SAFETY: the kernel maps our program headers at `AT_PHDR`
+    let headers = unsafe { slice::from_raw_parts(phdr as *const ProgramHeader, phnum) };
+    let interp = headers
+        .iter()
+        .find(|header| header.p_type == libc::PT_INTERP)?;
+
+    // position independent executables are loaded away from their addresses
+    let base = headers
+        .iter()
+        .find(|header| header.p_type == libc::PT_PHDR)
+        .map_or(0, |header| phdr - header.p_vaddr as usize);
+
+    // SAFETY: `PT_INTERP` is a string in a segment that stays mapped
+    Some(unsafe { CStr::from_ptr((base + interp.p_vaddr as usize) as *const libc::c_char) })
+}
+
+/// Whether a dynamic loader starting with `prefix` is installed
+fn has_loader(prefix: &str) -> bool {
+    ["/lib", "/lib64"].iter().any(|dir| {
+        fs::read_dir(dir).is_ok_and(|mut entries| {
+            entries.any(|entry| {
+                entry.is_ok_and(|entry| entry.file_name().as_bytes().starts_with(prefix.as_bytes()))
+            })
+        })
+    })
+}
+
+/// The operating system for `-o`, which is about the userspace running on
+/// the kernel rather than the kernel itself. Android is recognized by its
+/// system properties, musl and glibc by the dynamic loader we were started
+/// with or, for static executables, by the ones installed. If all else
+/// fails it's the libc we were built for.
+fn operating_system(sysname: &[u8]) -> &'static [u8] {
+    if sysname != b"Linux" {
+        return b"unknown";
+    }
+
+    const ANDROID_PROPERTIES: [&str; 3] = [
+        "/system/build.prop",
+        "/dev/__properties__",
+        "/property_contexts",
+    ];
+
+    if ANDROID_PROPERTIES
+        .iter()
+        .any(|path| fs::exists(path).unwrap_or(false))
+    {
+        return b"Android";
+    }
+
+    if let Some(interp) = interpreter() {
+        let interp = interp.to_bytes();
+        let name = interp.rsplit(|&byte| byte == b'/').next().unwrap_or(interp);
+
+        if name.starts_with(b"ld-musl-") {
+            return b"Linux";
+        }
+
+        if name.starts_with(b"linker") {
+            return b"Android";
+        }
+
+        if name.starts_with(b"ld-linux") || name.starts_with(b"ld64.so") {
+            return b"GNU/Linux";
+        }
+    } else if has_loader("ld-musl-") {
+        return b"Linux";
+    } else if has_loader("ld-linux") || has_loader("ld64.so") {
+        return b"GNU/Linux";
+    }
+
+    if cfg!(target_os = "android") {
+        b"Android"
+    } else if cfg!(target_env = "musl") {
+        b"Linux"
+    } else {
+        b"GNU/Linux"
+    }
+}
+
+/// `arch`, which is `uname -m`
+pub fn arch() -> Result {
+    let mut stdout = stdout();
+
+    cli!("arch", stdout, #error);
+
+    stdout.write_all(uname().machine().to_bytes())?;
+    stdout.write_all(b"\n")?;
+    stdout.flush()?;
+
+    Ok(())
+}
+
 pub fn main() -> Result {
     let mut info_mask = Info::empty();
     let mut stdout = stdout();
@@ -71,30 +253,25 @@ pub fn main() -> Result {
         write_field(uname.machine().to_bytes())?;
     }
 
-    #[allow(clippy::collapsible_if)]
+    // like GNU, `-a` leaves out what we couldn't figure out
     if info_mask.contains(Info::PROCESSOR) {
-        if !info_mask.is_all() {
-            // TODO: figure out if there is anything to do here
-            write_field(b"unknown")?;
+        match processor(uname.machine().to_bytes()) {
+            Some(processor) => write_field(&processor)?,
+            None if !info_mask.is_all() => write_field(b"unknown")?,
+            None => {}
         }
     }
 
-    #[allow(clippy::collapsible_if)] // FIXME: remove this once we figure out what to do below
     if info_mask.contains(Info::HARDWARE_PLATFORM) {
-        if !info_mask.is_all() {
-            // TODO: figure out if there is anything to do here
-            write_field(b"unknown")?;
+        match hardware_platform(uname.machine().to_bytes()) {
+            Some(platform) => write_field(platform)?,
+            None if !info_mask.is_all() => write_field(b"unknown")?,
+            None => {}
         }
     }
 
-    #[allow(clippy::collapsible_if)]
     if info_mask.contains(Info::OPERATING_SYSTEM) {
-        let os: &[u8] = match uname.sysname().to_bytes() {
-            b"Linux" => b"GNU/Linux",
-            _ => b"unknown",
-        };
-
-        write_field(os)?;
+        write_field(operating_system(uname.sysname().to_bytes()))?;
     }
 
     stdout.write_all(b"\n")?;
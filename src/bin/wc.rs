@@ -1,9 +1,25 @@
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, BufWriter, Write, stdin, stdout},
+    ffi::OsString,
+    fs::{self, File, Metadata},
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write, stdin, stdout},
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::MetadataExt,
+    },
+    path::{Path, PathBuf},
+    process::exit,
 };
 
-use puppyutils::{Result, cli};
+use puppyutils::{
+    Result, argmatch, cli_with_args, error_message, missing_argument, read_uninterrupted, std_file,
+    utf8::{self, Decoded},
+};
+use sap::Parser;
+
+const BUFFER_SIZE: usize = 256 * 1024;
+/// Bytes of plain ASCII text counted at once by `count_ascii`,
+/// past this size the compiler stops unrolling it
+const BLOCK: usize = 256;
 
 bitflags::bitflags! {
     struct Flags: u8 {
@@ -11,35 +27,311 @@ bitflags::bitflags! {
         const WORDS = 1 << 1;
         const CHARS = 1 << 2;
         const BYTES = 1 << 3;
+        const MAX_LINE_LENGTH = 1 << 4;
     }
 }
 
-fn count_lines<R: BufRead>(mut reader: R) -> Result<(usize, usize, usize, usize)> {
-    let mut lines = 0usize;
-    let mut words = 0usize;
-    let mut chars = 0usize;
-    let mut bytes = 0usize;
-    let mut line = String::new();
+/// When to print the line with the total counts
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Total {
+    /// Only with more than one operand
+    Auto,
+    Always,
+    /// Instead of the counts of each operand
+    Only,
+    Never,
+}
+
+impl Total {
+    const NAMES: [(&str, Total); 4] = [
+        ("auto", Total::Auto),
+        ("always", Total::Always),
+        ("only", Total::Only),
+        ("never", Total::Never),
+    ];
+}
+
+#[derive(Default)]
+struct Counts {
+    lines: u64,
+    words: u64,
+    chars: u64,
+    bytes: u64,
+    max_line_length: u64,
+}
+
+impl Counts {
+    fn add(&mut self, other: &Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.chars += other.chars;
+        self.bytes += other.bytes;
+        self.max_line_length = self.max_line_length.max(other.max_line_length);
+    }
+}
+
+/// Counts `file` into `counts`, doing only the work the counts in `flags`
+/// need. What was read before an error stays counted.
+fn count(mut file: File, flags: &Flags, buf: &mut [u8], counts: &mut Counts) -> io::Result<()> {
+    if flags.intersects(Flags::WORDS | Flags::CHARS | Flags::MAX_LINE_LENGTH) {
+        count_text(&mut file, flags, buf, counts)
+    } else if flags.contains(Flags::LINES) {
+        read_blocks(&mut file, buf, |block| {
+            counts.lines += count_newlines(block) as u64;
+            counts.bytes += block.len() as u64;
+        })
+    } else {
+        count_bytes(&mut file, buf, &mut counts.bytes)
+    }
+}
 
+fn read_blocks<F: FnMut(&[u8])>(file: &mut File, buf: &mut [u8], mut f: F) -> io::Result<()> {
     loop {
-        let read_bytes = reader.read_line(&mut line)?;
+        let len = read_uninterrupted(file, buf)?;
+        if len == 0 {
+            return Ok(());
+        }
+
+        f(&buf[..len]);
+    }
+}
+
+/// Like GNU, the size of a regular file comes from `fstat` and only what is
+/// past its last block is read, in case the file grew or lies about its size
+fn count_bytes(file: &mut File, buf: &mut [u8], bytes: &mut u64) -> io::Result<()> {
+    if let Ok(meta) = file.metadata()
+        && meta.is_file()
+        && let Ok(pos) = file.stream_position()
+    {
+        let end = meta.size();
+        let hi = end - end % (meta.blksize() + 1);
+
+        if pos < hi && file.seek(SeekFrom::Start(hi)).is_ok() {
+            *bytes = hi - pos;
+        }
+    }
+
+    read_blocks(file, buf, |block| *bytes += block.len() as u64)
+}
+
+/// Counts newlines in lanes of bytes that are added up before they can
+/// overflow, which the compiler turns into vector compares and adds
+fn count_newlines(bytes: &[u8]) -> usize {
+    // fewer lanes don't get vectorized
+    const LANES: usize = 64;
+
+    let mut blocks = bytes.chunks_exact(LANES * 255);
+    let mut count = 0;
+
+    for block in &mut blocks {
+        let mut lanes = [0u8; LANES];
+
+        for chunk in block.chunks_exact(LANES) {
+            let chunk: &[u8; LANES] = chunk.try_into().unwrap();
+
+            for i in 0..LANES {
+                lanes[i] += u8::from(chunk[i] == b'\n');
+            }
+        }
+
+        count += lanes.iter().map(|&lane| usize::from(lane)).sum::<usize>();
+    }
+
+    count
+        + blocks
+            .remainder()
+            .iter()
+            .filter(|&&byte| byte == b'\n')
+            .count()
+}
+
+/// Counts like GNU `wc` in a UTF-8 locale. Lines are newline bytes, characters
+/// are the valid UTF-8 sequences and words are runs of printable characters
+/// between spaces. Invalid bytes only count as bytes. Lines are measured
+/// in terminal columns, only characters past ASCII need a lookup.
+fn count_text(
+    file: &mut impl Read,
+    flags: &Flags,
+    buf: &mut [u8],
+    counts: &mut Counts,
+) -> io::Result<()> {
+    let measure = flags.contains(Flags::MAX_LINE_LENGTH);
+    let mut in_word = false;
+    // the column the next character of the current line goes to
+    let mut column = 0;
+    // the start of a character cut off by the end of the last read
+    let mut carry = 0;
+
+    loop {
+        let len = read_uninterrupted(file, &mut buf[carry..])?;
+        counts.bytes += len as u64;
+
+        let eof = len == 0;
+        let end = carry + len;
+        let mut pos = 0;
+        // where characters have to be decoded one by one until
+        let mut slow_until = 0;
+
+        while pos < end {
+            if pos >= slow_until
+                && let Some(block) = buf[..end].get(pos..pos + BLOCK)
+            {
+                if count_ascii(block.try_into().unwrap(), &mut in_word, counts) {
+                    if measure {
+                        measure_ascii(block, &mut column, &mut counts.max_line_length);
+                    }
+                    pos += BLOCK;
+                    continue;
+                }
+
+                slow_until = pos + BLOCK;
+            }
+
+            let (c, size) = match utf8::decode(&buf[pos..end]) {
+                Decoded::Char(c, size) => (c, size),
+                Decoded::Invalid => {
+                    pos += 1;
+                    continue;
+                }
+                Decoded::Incomplete if eof => {
+                    pos += 1;
+                    continue;
+                }
+                Decoded::Incomplete => break,
+            };
+
+            pos += size;
+            counts.chars += 1;
+
+            if c == u32::from(b'\n') {
+                counts.lines += 1;
+            }
+
+            if measure {
+                match c {
+                    // like GNU, carriage returns and form feeds also end lines
+                    0x0a | 0x0c | 0x0d => {
+                        counts.max_line_length = counts.max_line_length.max(column);
+                        column = 0;
+                    }
+                    0x09 => column += 8 - column % 8,
+                    0x20..=0x7e => column += 1,
+                    _ => column += utf8::width(c) as u64,
+                }
+            }
+
+            if is_space(c) {
+                in_word = false;
+            } else if utf8::is_print(c) && !in_word {
+                in_word = true;
+                counts.words += 1;
+            }
+        }
 
-        if read_bytes == 0 {
-            break;
+        if eof {
+            counts.max_line_length = counts.max_line_length.max(column);
+            return Ok(());
         }
 
-        bytes += read_bytes;
-        lines += 1;
-        words += line.split_whitespace().count();
-        chars += line.chars().count();
-        line.clear();
+        buf.copy_within(pos..end, 0);
+        carry = end - pos;
     }
+}
+
+/// Counts a block made only of ASCII spaces and printable characters, where a
+/// word starts at each printable character that follows a space. The lanes
+/// are laid out for the compiler to vectorize, like in `count_newlines`.
+/// Returns false without counting anything for any other block.
+// inlined into `count_text` it no longer gets vectorized
+#[inline(never)]
+fn count_ascii(block: &[u8; BLOCK], in_word: &mut bool, counts: &mut Counts) -> bool {
+    const LANES: usize = 32;
+
+    let mut other = [0u8; LANES];
+    let mut newlines = [0u8; LANES];
 
-    Ok((lines, words, chars, bytes))
+    for chunk in block.chunks_exact(LANES) {
+        let chunk: &[u8; LANES] = chunk.try_into().unwrap();
+
+        for i in 0..LANES {
+            other[i] |= u8::from(!is_ascii_space(chunk[i]) && !is_ascii_print(chunk[i]));
+            newlines[i] += u8::from(chunk[i] == b'\n');
+        }
+    }
+
+    if other.iter().any(|&lane| lane != 0) {
+        return false;
+    }
+
+    let mut starts = [0u8; LANES];
+
+    for j in (0..BLOCK).step_by(LANES) {
+        for (i, start) in starts.iter_mut().enumerate() {
+            let k = j + i;
+            let after_space = match k {
+                0 => !*in_word,
+                _ => !is_ascii_print(block[k - 1]),
+            };
+
+            *start += u8::from(after_space && is_ascii_print(block[k]));
+        }
+    }
+
+    let sum = |lanes: [u8; LANES]| lanes.iter().map(|&lane| usize::from(lane)).sum::<usize>();
+
+    counts.lines += sum(newlines) as u64;
+    counts.words += sum(starts) as u64;
+    counts.chars += BLOCK as u64;
+    *in_word = is_ascii_print(block[BLOCK - 1]);
+
+    true
+}
+
+/// Measures the lines in a block `count_ascii` took, where all bytes but
+/// tabs and the ones ending lines are a column wide, except vertical tabs
+fn measure_ascii(block: &[u8], column: &mut u64, longest: &mut u64) {
+    for &byte in block {
+        match byte {
+            b'\n' | b'\r' | 0x0c => {
+                *longest = (*longest).max(*column);
+                *column = 0;
+            }
+            b'\t' => *column += 8 - *column % 8,
+            0x0b => {}
+            _ => *column += 1,
+        }
+    }
+}
+
+#[inline]
+fn is_ascii_space(byte: u8) -> bool {
+    byte == b' ' || (b'\t'..=b'\r').contains(&byte)
+}
+
+#[inline]
+fn is_ascii_print(byte: u8) -> bool {
+    (b'!'..=b'~').contains(&byte)
+}
+
+/// Word separators: what glibc's `iswspace` takes as space in UTF-8
+/// locales, plus the no-break spaces GNU `wc` adds to them
+fn is_space(c: u32) -> bool {
+    matches!(
+        c,
+        0x09..=0x0d
+            | 0x20
+            | 0xa0
+            | 0x1680
+            | 0x2000..=0x200a
+            | 0x202f
+            | 0x205f
+            | 0x2060
+            | 0x3000
+    )
 }
 
 enum StdioOrValue {
-    Value(String),
+    Value(PathBuf),
     Stdio,
 }
 
@@ -49,18 +341,198 @@ impl<T: AsRef<str>> From<T> for StdioOrValue {
     }
 }
 
+impl StdioOrValue {
+    fn open(&self) -> io::Result<File> {
+        match self {
+            Self::Value(path) => File::open(path),
+            Self::Stdio => std_file(stdin()),
+        }
+    }
+
+    fn metadata(&self) -> io::Result<Metadata> {
+        match self {
+            Self::Value(path) => fs::metadata(path),
+            Self::Stdio => std_file(stdin())?.metadata(),
+        }
+    }
+
+    fn name(&self) -> &Path {
+        match self {
+            Self::Value(path) => path,
+            Self::Stdio => Path::new("-"),
+        }
+    }
+}
+
+/// An operand, or the error to report in its place
+type Operand = Result<StdioOrValue, String>;
+
+/// The NUL separated operands listed in the file given to `--files0-from`,
+/// read one at a time so lists of millions of files can be piped in
+struct Files0 {
+    from: String,
+    names: io::Split<BufReader<File>>,
+    item: u64,
+    /// Whether the list is a regular file, which is read whole
+    /// up front like GNU to align the counts of its operands
+    regular: bool,
+}
+
+impl Files0 {
+    fn open(from: String) -> Self {
+        let file = if from == "-" {
+            std_file(stdin())
+        } else {
+            File::open(&from)
+        };
+
+        match file {
+            Ok(file) => Self {
+                regular: file.metadata().is_ok_and(|meta| meta.is_file()),
+                from,
+                names: BufReader::new(file).split(0),
+                item: 0,
+            },
+            Err(err) => {
+                eprintln!(
+                    "wc: cannot open '{from}' for reading: {}",
+                    error_message(&err)
+                );
+                exit(1);
+            }
+        }
+    }
+}
+
+impl Iterator for Files0 {
+    type Item = Operand;
+
+    fn next(&mut self) -> Option<Operand> {
+        let name = match self.names.next()? {
+            Ok(name) => name,
+            Err(err) => {
+                eprintln!("wc: {}: read error: {}", self.from, error_message(&err));
+                exit(1);
+            }
+        };
+
+        self.item += 1;
+
+        Some(match &name[..] {
+            b"" => Err(format!(
+                "{}:{}: invalid zero-length file name",
+                self.from, self.item
+            )),
+            b"-" if self.from == "-" => {
+                Err("when reading file names from stdin, no file name of '-' allowed".to_owned())
+            }
+            b"-" => Ok(StdioOrValue::Stdio),
+            _ => Ok(StdioOrValue::Value(OsString::from_vec(name).into())),
+        })
+    }
+}
+
+/// Parses the argument of `--total`, which like GNU can be abbreviated
+fn parse_total(arg: Option<String>) -> Total {
+    let Some(arg) = arg else {
+        missing_argument("wc", "--total");
+        exit(1);
+    };
+
+    argmatch("wc", "total", &arg, &Total::NAMES)
+}
+
+/// The width the counts are right aligned to. Like GNU, it fits the sum
+/// of the sizes of the regular files, so the total fits too, and is at
+/// least 7 when something else like a pipe is read. There's nothing to
+/// align a single count of a single operand to.
+fn number_width(files: &[Operand], flags: &Flags) -> usize {
+    if files.len() == 1 && flags.bits().count_ones() == 1 {
+        return 1;
+    }
+
+    let mut minimum = 1;
+    let mut total: u64 = 0;
+
+    for meta in files
+        .iter()
+        .flatten()
+        .filter_map(|file| file.metadata().ok())
+    {
+        if meta.is_file() {
+            total = total.saturating_add(meta.size());
+        } else {
+            minimum = 7;
+        }
+    }
+
+    let mut buf = itoa::Buffer::new();
+
+    buf.format(total).len().max(minimum)
+}
+
+fn write_counts<W: Write>(
+    out: &mut W,
+    counts: &Counts,
+    flags: &Flags,
+    width: usize,
+    name: Option<&Path>,
+) -> io::Result<()> {
+    let mut buf = itoa::Buffer::new();
+    let mut first = true;
+
+    for (flag, num) in [
+        (Flags::LINES, counts.lines),
+        (Flags::WORDS, counts.words),
+        (Flags::CHARS, counts.chars),
+        (Flags::BYTES, counts.bytes),
+        (Flags::MAX_LINE_LENGTH, counts.max_line_length),
+    ] {
+        if !flags.contains(flag) {
+            continue;
+        }
+
+        if !first {
+            out.write_all(b" ")?;
+        }
+
+        write!(out, "{:>width$}", buf.format(num))?;
+        first = false;
+    }
+
+    if let Some(name) = name {
+        out.write_all(b" ")?;
+        out.write_all(name.as_os_str().as_bytes())?;
+    }
+
+    out.write_all(b"\n")
+}
+
 pub fn main() -> Result {
     let mut stdout = stdout();
+    let mut args = Parser::from_env()?;
     let mut files: Vec<StdioOrValue> = Vec::new();
 
     let mut flags = Flags::empty();
+    let mut total = Total::Auto;
+    let mut files0_from = None;
 
-    cli! {
-        "wc", stdout, #error
+    cli_with_args! {
+        args, "wc", stdout, #error
         Short('l') | Long("lines") => flags |= Flags::LINES
         Short('w') | Long("words") => flags |= Flags::WORDS
         Short('m') | Long("chars") => flags |= Flags::CHARS
         Short('c') | Long("bytes") => flags |= Flags::BYTES
+        Short('L') | Long("max-line-length") => flags |= Flags::MAX_LINE_LENGTH
+        Long("total") => total = parse_total(args.value())
+        Long("files0-from") => {
+            let Some(from) = args.value() else {
+                missing_argument("wc", "--files0-from");
+                exit(1);
+            };
+
+            files0_from = Some(from);
+        }
         Value(value) => {
             files.push(value.into());
         }
@@ -73,50 +545,279 @@ pub fn main() -> Result {
 
     let mut stdout = BufWriter::new(stdout);
 
-    // If no files, read from stdin
-    if files.is_empty() {
-        files.push(StdioOrValue::Stdio);
-    }
+    // If no files, read from stdin, which then has no name to print
+    let named = !files.is_empty() || files0_from.is_some();
+
+    let (operands, width): (Box<dyn Iterator<Item = Operand>>, _) = match files0_from {
+        Some(from) => {
+            if let Some(file) = files.first() {
+                eprintln!("wc: extra operand '{}'", file.name().display());
+                eprintln!("file operands cannot be combined with --files0-from");
+                eprintln!("Try 'wc --help' for more information.");
+                exit(1);
+            }
+
+            let files0 = Files0::open(from);
+
+            if files0.regular && total != Total::Only {
+                let files: Vec<_> = files0.collect();
+                let width = number_width(&files, &flags);
+
+                (Box::new(files.into_iter()), width)
+            } else {
+                (Box::new(files0), 1)
+            }
+        }
+        None => {
+            if files.is_empty() {
+                files.push(StdioOrValue::Stdio);
+            }
+
+            let files: Vec<_> = files.into_iter().map(Ok).collect();
+            let width = match total {
+                Total::Only => 1,
+                _ => number_width(&files, &flags),
+            };
+
+            (Box::new(files.into_iter()), width)
+        }
+    };
+
+    let mut buf = vec![0; BUFFER_SIZE];
+    let mut totals = Counts::default();
+    let mut operand_count = 0;
+    let mut status = 0;
+
+    for operand in operands {
+        operand_count += 1;
 
-    for path in files {
-        let (lines, words, chars, bytes) = match path {
-            StdioOrValue::Value(ref value) => count_lines(BufReader::new(File::open(value)?))?,
-            StdioOrValue::Stdio => count_lines(BufReader::new(stdin()))?,
+        let path = match operand {
+            Ok(path) => path,
+            Err(message) => {
+                stdout.flush()?;
+                eprintln!("wc: {message}");
+                status = 1;
+                continue;
+            }
         };
 
-        let mut first = true;
-        let mut write_num = |num: usize| -> Result<()> {
-            if !first {
-                stdout.write_all(b" ")?;
+        let name = path.name();
+
+        let file = match path.open() {
+            Ok(file) => file,
+            Err(err) => {
+                stdout.flush()?;
+                eprintln!("wc: {}: {}", name.display(), error_message(&err));
+                status = 1;
+                continue;
             }
-            stdout.write_all(itoa::Buffer::new().format(num).as_bytes())?;
-            first = false;
-            Ok(())
         };
 
-        if flags.contains(Flags::LINES) {
-            write_num(lines)?;
+        let mut counts = Counts::default();
+
+        // like GNU, the counts of a file that couldn't be read to the end are still printed
+        if let Err(err) = count(file, &flags, &mut buf, &mut counts) {
+            stdout.flush()?;
+            eprintln!("wc: {}: {}", name.display(), error_message(&err));
+            status = 1;
         }
 
-        if flags.contains(Flags::WORDS) {
-            write_num(words)?;
+        if total != Total::Only {
+            write_counts(&mut stdout, &counts, &flags, width, named.then_some(name))?;
         }
 
-        if flags.contains(Flags::CHARS) {
-            write_num(chars)?;
+        totals.add(&counts);
+    }
+
+    let print_total = match total {
+        Total::Auto => operand_count > 1,
+        Total::Always | Total::Only => true,
+        Total::Never => false,
+    };
+
+    if print_total {
+        let name = (total != Total::Only).then_some(Path::new("total"));
+        write_counts(&mut stdout, &totals, &flags, width, name)?;
+    }
+
+    stdout.flush()?;
+
+    if status != 0 {
+        exit(status);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands out `text` in reads of at most `len` bytes
+    struct Trickle<'a> {
+        text: &'a [u8],
+        len: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.len.min(buf.len()).min(self.text.len());
+            buf[..len].copy_from_slice(&self.text[..len]);
+            self.text = &self.text[len..];
+            Ok(len)
         }
+    }
 
-        if flags.contains(Flags::BYTES) {
-            write_num(bytes)?;
+    fn count_all(text: &[u8], read_len: usize) -> Counts {
+        let flags = Flags::WORDS | Flags::CHARS | Flags::MAX_LINE_LENGTH;
+        let mut counts = Counts::default();
+        let mut buf = vec![0; BUFFER_SIZE];
+        let mut file = Trickle {
+            text,
+            len: read_len,
+        };
+
+        count_text(&mut file, &flags, &mut buf, &mut counts).unwrap();
+        counts
+    }
+
+    /// A block of ASCII text filled with `pattern`
+    fn block(pattern: &[u8]) -> [u8; BLOCK] {
+        let mut block = [0; BLOCK];
+        for (byte, &c) in block.iter_mut().zip(pattern.iter().cycle()) {
+            *byte = c;
         }
+        block
+    }
+
+    #[test]
+    fn newlines() {
+        // around the lane width and the size of the blocks of lanes
+        for len in [
+            0,
+            1,
+            63,
+            64,
+            65,
+            255,
+            64 * 255 - 1,
+            64 * 255,
+            64 * 255 + 1,
+            100_003,
+        ] {
+            let text: Vec<u8> = (0..len)
+                .map(|i| if i % 3 == 0 { b'\n' } else { b'a' })
+                .collect();
+            let expected = text.iter().filter(|&&byte| byte == b'\n').count();
 
-        if let StdioOrValue::Value(path) = path {
-            stdout.write_all(b" ")?;
-            stdout.write_all(path.as_bytes())?;
+            assert_eq!(count_newlines(&text), expected, "length {len}");
         }
 
-        stdout.write_all(b"\n")?;
+        // every lane counts up to 255 in each block
+        let text = vec![b'\n'; 64 * 255 * 2 + 7];
+        assert_eq!(count_newlines(&text), text.len());
     }
 
-    Ok(())
+    #[test]
+    fn ascii_blocks() {
+        let mut counts = Counts::default();
+        let mut in_word = false;
+
+        assert!(count_ascii(&block(b"ab cd\n"), &mut in_word, &mut counts));
+        assert_eq!(counts.lines, BLOCK as u64 / 6);
+        assert_eq!(counts.chars, BLOCK as u64);
+        // 42 patterns and "ab c"
+        assert_eq!(counts.words, 2 * 42 + 2);
+        assert!(in_word);
+
+        // a word going on from the last block isn't counted again
+        let mut counts = Counts::default();
+        assert!(count_ascii(&block(b"x"), &mut in_word, &mut counts));
+        assert_eq!(counts.words, 0);
+
+        let mut counts = Counts::default();
+        let mut in_word = false;
+        assert!(count_ascii(&block(b" "), &mut in_word, &mut counts));
+        assert_eq!((counts.words, counts.lines), (0, 0));
+        assert!(!in_word);
+    }
+
+    #[test]
+    fn ascii_blocks_refuse_other_bytes() {
+        for other in [0x01, 0x7f, 0x80, 0xc3, 0xff] {
+            let mut text = block(b"ab cd\n");
+            text[BLOCK - 1] = other;
+            let mut counts = Counts::default();
+            let mut in_word = false;
+
+            assert!(!count_ascii(&text, &mut in_word, &mut counts));
+            assert_eq!(
+                (counts.lines, counts.words, counts.chars),
+                (0, 0, 0),
+                "byte {other:#x}"
+            );
+        }
+    }
+
+    #[test]
+    fn text() {
+        let counts = count_all("héllo wörld\n日本\t語\n".as_bytes(), BUFFER_SIZE);
+        assert_eq!(counts.bytes, 25);
+        assert_eq!(counts.chars, 17);
+        assert_eq!(counts.words, 4);
+        assert_eq!(counts.lines, 2);
+        assert_eq!(counts.max_line_length, 11);
+    }
+
+    #[test]
+    fn invalid_and_truncated_text() {
+        // invalid bytes are neither characters nor spaces
+        let counts = count_all(b"a\xffb \xc3\x28 \xed\xa0\x80", BUFFER_SIZE);
+        assert_eq!(counts.bytes, 10);
+        assert_eq!(counts.chars, 5);
+        assert_eq!(counts.words, 2);
+
+        // the start of a character at the end is only bytes
+        let counts = count_all(b"ab \xe2\x82", BUFFER_SIZE);
+        assert_eq!(counts.bytes, 5);
+        assert_eq!(counts.chars, 3);
+        assert_eq!(counts.words, 1);
+    }
+
+    #[test]
+    fn ascii_line_lengths() {
+        let mut text = "a\tbc".repeat(100);
+        text += "\r";
+        text += &"x \x0b".repeat(150);
+        text += "\x0c\n\t";
+        let text = text.repeat(3);
+
+        // reads of a byte never fill a block, so every byte is decoded
+        let fast = count_all(text.as_bytes(), BUFFER_SIZE);
+        let slow = count_all(text.as_bytes(), 1);
+
+        assert_eq!(fast.max_line_length, slow.max_line_length);
+        assert_eq!(fast.max_line_length, 810);
+        assert_eq!(fast.words, slow.words);
+    }
+
+    #[test]
+    fn characters_split_across_reads() {
+        let text = "日本語 ça €uro 🦀\n".repeat(40);
+        let whole = count_all(text.as_bytes(), BUFFER_SIZE);
+
+        for read_len in [1, 2, 3, 5, BLOCK - 1, BLOCK + 1] {
+            let split = count_all(text.as_bytes(), read_len);
+
+            assert_eq!(
+                (split.bytes, split.chars, split.words, split.lines),
+                (whole.bytes, whole.chars, whole.words, whole.lines),
+                "reads of {read_len}"
+            );
+            assert_eq!(split.max_line_length, whole.max_line_length);
+        }
+
+        assert_eq!(whole.chars, 14 * 40);
+        assert_eq!(whole.words, 4 * 40);
+    }
 }
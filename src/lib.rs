@@ -2,11 +2,18 @@ use std::{
     borrow::Cow,
     ffi::c_uint,
     fmt::{Debug, Display},
-    fs, io,
+    fs::{self, File},
+    io::{self, Read},
+    os::fd::AsFd,
 };
 
 use xenia::{Mode, umask};
 
+pub mod args;
+pub mod mode;
+pub mod time;
+pub mod utf8;
+
 pub type Result<T = (), E = Exit> = std::result::Result<T, E>;
 
 /// Represents the type of error
@@ -96,17 +103,24 @@ macro_rules! _cli_impl {
 
     // TODO: we can prob make a default case that uses while
     ($args:ident, $name:literal, $stdout:ident, $loop_type:tt, $($item:pat => $matcher:expr)*) => {
+        $crate::_cli_impl!(
+            $args, #texts $crate::version_text!($name), $crate::help_text!($name),
+            $stdout, $loop_type, $($item => $matcher)*
+        )
+    };
+
+    ($args:ident, #texts $version:expr, $help:expr, $stdout:ident, $loop_type:tt, $($item:pat => $matcher:expr)*) => {
         $loop_type let Some(arg) = $args.forward()? {
             use std::io::Write;
             use sap::Argument::*;
             match arg {
                 Long("version") => {
-                    $stdout.write_all($crate::version_text!($name).as_bytes())?;
+                    $stdout.write_all($version.as_bytes())?;
                     $stdout.flush()?;
                     std::process::exit(0);
                 }
                 Long("help") => {
-                    $stdout.write_all($crate::help_text!($name).as_bytes())?;
+                    $stdout.write_all($help.as_bytes())?;
                     $stdout.flush()?;
                     std::process::exit(0);
                 }
@@ -152,6 +166,12 @@ macro_rules! cli_with_args {
     ($args:ident, $name:literal, $stdout:ident, #error $($item:pat => $matcher:expr)*) => {
         $crate::_cli_impl!($args, $name, $stdout, while, $($item => $matcher)* arg => return Err(arg.unexpected().into()))
     };
+
+    // for utilities that go by more than one name, the texts for
+    // `--version` and `--help` are picked at runtime
+    ($args:ident, #texts $version:expr, $help:expr, $stdout:ident $($item:pat => $matcher:expr)*) => {
+        $crate::_cli_impl!($args, #texts $version, $help, $stdout, while, $($item => $matcher)*)
+    };
 }
 
 /// Gets the umask of the calling process
@@ -186,3 +206,78 @@ pub fn get_umask() -> Mode {
 
     current_umask
 }
+
+/// Picks the value of the argument `arg` of `--option` among `choices` like
+/// gnulib's `argmatch`, which takes abbreviations as long as they can only
+/// mean one value. Otherwise exits listing the valid arguments, with those
+/// that mean the same value next to each other in `choices` on one line.
+pub fn argmatch<T: Copy + PartialEq>(
+    program: &str,
+    option: &str,
+    arg: &str,
+    choices: &[(&str, T)],
+) -> T {
+    if let Some(&(_, value)) = choices.iter().find(|(name, _)| *name == arg) {
+        return value;
+    }
+
+    let mut matches = choices.iter().filter(|(name, _)| name.starts_with(arg));
+
+    match matches.next() {
+        Some(&(_, value)) if matches.all(|&(_, other)| other == value) => return value,
+        Some(_) => eprintln!("{program}: ambiguous argument '{arg}' for '--{option}'"),
+        None => eprintln!("{program}: invalid argument '{arg}' for '--{option}'"),
+    }
+
+    eprintln!("Valid arguments are:");
+
+    for (i, (name, value)) in choices.iter().enumerate() {
+        match i.checked_sub(1).map(|prev| &choices[prev]) {
+            Some((_, prev)) if prev == value => eprint!(", '{name}'"),
+            Some(_) => eprint!("\n  - '{name}'"),
+            None => eprint!("  - '{name}'"),
+        }
+    }
+
+    eprintln!("\nTry '{program} --help' for more information.");
+    std::process::exit(1);
+}
+
+/// Reports `option` given without its argument like `getopt` does, callers
+/// exit with the status of their usage errors
+pub fn missing_argument(program: &str, option: &str) {
+    match option.strip_prefix("--") {
+        Some(_) => eprintln!("{program}: option '{option}' requires an argument"),
+        None => eprintln!(
+            "{program}: option requires an argument -- '{}'",
+            &option[1..]
+        ),
+    }
+
+    eprintln!("Try '{program} --help' for more information.");
+}
+
+/// Reads into `buf` like `Read::read`, retrying reads interrupted by a signal
+pub fn read_uninterrupted<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        match reader.read(buf) {
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            result => return result,
+        }
+    }
+}
+
+/// The `strerror` text of an I/O error, without the `(os error N)` suffix std adds
+pub fn error_message(err: &io::Error) -> String {
+    let message = err.to_string();
+
+    match message.find(" (os error") {
+        Some(index) => message[..index].to_owned(),
+        None => message,
+    }
+}
+
+/// A standard stream as a `File`, to `fstat` it and do unbuffered I/O
+pub fn std_file<Fd: AsFd>(stream: Fd) -> io::Result<File> {
+    Ok(stream.as_fd().try_clone_to_owned()?.into())
+}
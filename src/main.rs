@@ -1,4 +1,4 @@
-use std::{borrow::Cow, env::current_exe, os::unix::ffi::OsStrExt, path::PathBuf};
+use std::{borrow::Cow, env::current_exe, os::unix::ffi::OsStrExt};
 
 use puppyutils::{Exit, Result};
 
@@ -26,7 +26,9 @@ fn main() -> Result {
         .ok_or::<Exit>("Failed to get util name".into())?;
 
     match util.as_bytes() {
+        b"arch" => bin::uname::arch(),
         b"cat" => bin::cat::main(),
+        b"dir" => bin::ls::dir(),
         b"false" => bin::r#false::main(),
         b"ls" => bin::ls::main(),
         b"mkdir" => bin::mkdir::main(),
@@ -35,6 +37,7 @@ fn main() -> Result {
         b"true" => bin::r#true::main(),
         b"tty" => bin::tty::main(),
         b"uname" => bin::uname::main(),
+        b"vdir" => bin::ls::vdir(),
         b"wc" => bin::wc::main(),
         b"whoami" => bin::whoami::main(),
         b"yes" => bin::yes::main(),
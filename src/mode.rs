@@ -0,0 +1,165 @@
+use xenia::Mode;
+
+const SET_ID: u32 = 0o6000;
+const STICKY: u32 = 0o1000;
+const ALL: u32 = 0o7777;
+
+/// The result of applying a mode spec, `changed` holds the bits the spec
+/// set or cleared, which are the only ones it cares about
+#[derive(Clone, Copy)]
+pub struct ModeChange {
+    pub mode: Mode,
+    pub changed: Mode,
+}
+
+/// Applies a `chmod` style mode, either octal or symbolic like `u=rwx,go+rX`
+/// or `=755`, to `mode`. Like GNU, when no `ugoa` is given only the bits not in `umask`
+/// are affected, and the set-ID bits of directories are only cleared by
+/// spelling them out. Returns `None` if `spec` is invalid.
+pub fn apply_mode(spec: &str, mode: Mode, umask: Mode, dir: bool) -> Option<ModeChange> {
+    let mut mode = mode.bits() & ALL;
+    let umask = umask.bits() & ALL;
+
+    if !spec.is_empty() && spec.bytes().all(|byte| matches!(byte, b'0'..=b'7')) {
+        let value = u32::from_str_radix(spec, 8)
+            .ok()
+            .filter(|&value| value <= ALL)?;
+
+        // like GNU, short octal modes leave the set-ID bits of directories alone
+        let changed = if dir && spec.len() < 5 {
+            0o1777 | (value & SET_ID)
+        } else {
+            ALL
+        };
+
+        return Some(ModeChange {
+            mode: Mode::from_bits_retain((mode & !changed) | value),
+            changed: Mode::from_bits_retain(changed),
+        });
+    }
+
+    let mut changed = 0;
+
+    for clause in spec.split(',') {
+        let mut bytes = clause.bytes().peekable();
+        let mut who = 0;
+
+        while let Some(byte) = bytes.next_if(|byte| b"ugoa".contains(byte)) {
+            who |= match byte {
+                b'u' => 0o4700,
+                b'g' => 0o2070,
+                b'o' => 0o1007,
+                _ => ALL,
+            };
+        }
+
+        // a clause needs at least one operator
+        bytes.peek().filter(|byte| b"+-=".contains(byte))?;
+
+        while let Some(op) = bytes.next() {
+            if !b"+-=".contains(&op) {
+                return None;
+            }
+
+            // an octal mode like in `=755`, which like GNU takes no `ugoa`,
+            // leaves the umask alone and ends the clause
+            if bytes
+                .peek()
+                .is_some_and(|byte| (b'0'..=b'7').contains(byte))
+            {
+                if who != 0 {
+                    return None;
+                }
+
+                let mut value = 0;
+                while let Some(digit) = bytes.next_if(|byte| (b'0'..=b'7').contains(byte)) {
+                    value = value * 8 + u32::from(digit - b'0');
+                    if value > ALL {
+                        return None;
+                    }
+                }
+
+                if bytes.next().is_some() {
+                    return None;
+                }
+
+                match op {
+                    b'=' => {
+                        mode = value;
+                        changed = ALL;
+                    }
+                    b'+' => {
+                        mode |= value;
+                        changed |= value;
+                    }
+                    _ => {
+                        mode &= !value;
+                        changed |= value;
+                    }
+                }
+                break;
+            }
+
+            let mut value = 0;
+            let mut special = 0;
+
+            if let Some(class) = bytes.next_if(|byte| b"ugo".contains(byte)) {
+                let shift = match class {
+                    b'u' => 6,
+                    b'g' => 3,
+                    _ => 0,
+                };
+
+                value = ((mode >> shift) & 0o7) * 0o111;
+            } else {
+                while let Some(perm) = bytes.next_if(|byte| b"rwxXst".contains(byte)) {
+                    match perm {
+                        b'r' => value |= 0o444,
+                        b'w' => value |= 0o222,
+                        b'x' => value |= 0o111,
+                        b'X' if dir || mode & 0o111 != 0 => value |= 0o111,
+                        b's' => special |= SET_ID,
+                        b't' => special |= STICKY,
+                        _ => {}
+                    }
+                }
+            }
+
+            value |= special;
+
+            let affected = match who {
+                0 => ALL,
+                who => who,
+            };
+
+            value &= match who {
+                0 => !umask,
+                who => who,
+            };
+
+            match op {
+                b'=' => {
+                    // set-ID bits of directories survive unless mentioned
+                    let kept = if dir { SET_ID & !special } else { 0 };
+                    let cleared = affected & !kept;
+
+                    mode = (mode & !cleared) | (value & cleared);
+                    changed |= cleared;
+                }
+                b'+' => {
+                    mode |= value;
+                    changed |= value;
+                }
+                _ => {
+                    mode &= !value;
+                    changed |= value;
+                }
+            }
+        }
+    }
+
+    Some(ModeChange {
+        mode: Mode::from_bits_retain(mode),
+        changed: Mode::from_bits_retain(changed),
+    })
+}
@@ -0,0 +1,838 @@
+use std::{env, fs, io};
+
+pub mod parse;
+
+const SECS_PER_DAY: i64 = 86400;
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Number of days since 1970-01-01 for the given proleptic Gregorian date
+///
+/// # Panics
+///
+/// If the number doesn't fit, which no year of a timestamp gets near.
+pub const fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    match checked_days_from_civil(year, month, day) {
+        Some(days) => days,
+        None => panic!("year out of range"),
+    }
+}
+
+/// [`days_from_civil`], `None` if the number of days doesn't fit
+pub const fn checked_days_from_civil(year: i64, month: u8, day: u8) -> Option<i64> {
+    let year = match year.checked_sub((month <= 2) as i64) {
+        Some(year) => year,
+        None => return None,
+    };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    match era.checked_mul(146097) {
+        Some(days) => days.checked_add(doe - 719468),
+        None => None,
+    }
+}
+
+/// Inverse of [`days_from_civil`], returns `(year, month, day)`
+pub const fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    (year, month, day)
+}
+
+pub const fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub const fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// A broken down point in time, as `localtime` would return it
+#[derive(Debug, Clone, Copy)]
+pub struct DateTime<'a> {
+    pub year: i64,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32,
+    /// Days since Sunday
+    pub weekday: u8,
+    /// Days since January 1st
+    pub yearday: u16,
+    /// Seconds east of UTC
+    pub offset: i32,
+    pub abbreviation: &'a str,
+    pub timestamp: i64,
+}
+
+impl DateTime<'_> {
+    /// Formats the date according to a `strftime` style format string
+    ///
+    /// Supports the conversions used by `date(1)`, including the `-`, `_`,
+    /// `0` and `^` flags, field widths, `%N` and the `%:z` family.
+    pub fn format(&self, fmt: &[u8], out: &mut Vec<u8>) {
+        let mut iter = fmt.iter().copied().peekable();
+
+        while let Some(byte) = iter.next() {
+            if byte != b'%' {
+                out.push(byte);
+                continue;
+            }
+
+            let mut pad = None;
+            let mut upper = false;
+
+            while let Some(&flag @ (b'-' | b'_' | b'0' | b'^' | b'#')) = iter.peek() {
+                match flag {
+                    b'^' => upper = true,
+                    b'#' => {}
+                    _ => pad = Some(flag),
+                }
+                iter.next();
+            }
+
+            let mut width = None;
+            while let Some(&digit @ b'0'..=b'9') = iter.peek() {
+                width = Some(width.unwrap_or(0) * 10 + (digit - b'0') as usize);
+                iter.next();
+            }
+
+            let mut colons = 0;
+            while iter.peek() == Some(&b':') {
+                colons += 1;
+                iter.next();
+            }
+
+            let Some(conv) = iter.next() else {
+                out.push(b'%');
+                break;
+            };
+
+            let number = |out: &mut Vec<u8>, value: i64, digits: usize, default_pad: u8| {
+                let pad = pad.unwrap_or(default_pad);
+                let digits = width.unwrap_or(digits);
+                let mut buf = itoa::Buffer::new();
+                let formatted = buf.format(value.unsigned_abs());
+                if value < 0 {
+                    out.push(b'-');
+                }
+                if pad != b'-' {
+                    let fill = if pad == b'_' { b' ' } else { b'0' };
+                    for _ in formatted.len() + (value < 0) as usize..digits {
+                        out.push(fill);
+                    }
+                }
+                out.extend_from_slice(formatted.as_bytes());
+            };
+
+            let text = |out: &mut Vec<u8>, text: &str| {
+                if let Some(width) = width {
+                    let fill = if pad == Some(b'0') { b'0' } else { b' ' };
+                    for _ in text.len()..width {
+                        out.push(fill);
+                    }
+                }
+                if upper {
+                    out.extend(text.bytes().map(|b| b.to_ascii_uppercase()));
+                } else {
+                    out.extend_from_slice(text.as_bytes());
+                }
+            };
+
+            let hour12 = match self.hour % 12 {
+                0 => 12,
+                hour => hour,
+            };
+
+            match conv {
+                b'%' => out.push(b'%'),
+                b'n' => out.push(b'\n'),
+                b't' => out.push(b'\t'),
+                b'a' => text(out, &WEEKDAYS[self.weekday as usize][..3]),
+                b'A' => text(out, WEEKDAYS[self.weekday as usize]),
+                b'b' | b'h' => text(out, &MONTHS[self.month as usize - 1][..3]),
+                b'B' => text(out, MONTHS[self.month as usize - 1]),
+                b'C' => number(out, self.year.div_euclid(100), 2, b'0'),
+                b'd' => number(out, self.day as i64, 2, b'0'),
+                b'e' => number(out, self.day as i64, 2, b'_'),
+                b'g' => number(out, self.iso_week().0.rem_euclid(100), 2, b'0'),
+                b'G' => number(out, self.iso_week().0, 4, b'0'),
+                b'H' => number(out, self.hour as i64, 2, b'0'),
+                b'I' => number(out, hour12 as i64, 2, b'0'),
+                b'j' => number(out, self.yearday as i64 + 1, 3, b'0'),
+                b'k' => number(out, self.hour as i64, 2, b'_'),
+                b'l' => number(out, hour12 as i64, 2, b'_'),
+                b'm' => number(out, self.month as i64, 2, b'0'),
+                b'M' => number(out, self.minute as i64, 2, b'0'),
+                b'N' => {
+                    let mut digits = [b'0'; 9];
+                    let mut ns = self.nanosecond;
+                    for digit in digits.iter_mut().rev() {
+                        *digit = b'0' + (ns % 10) as u8;
+                        ns /= 10;
+                    }
+                    out.extend_from_slice(&digits[..width.unwrap_or(9).min(9)]);
+                }
+                b'p' => text(out, if self.hour < 12 { "AM" } else { "PM" }),
+                b'P' => text(out, if self.hour < 12 { "am" } else { "pm" }),
+                b's' => number(out, self.timestamp, 1, b'0'),
+                b'S' => number(out, self.second as i64, 2, b'0'),
+                b'u' => number(out, (self.weekday as i64 + 6) % 7 + 1, 1, b'0'),
+                b'U' => number(
+                    out,
+                    (self.yearday as i64 + 7 - self.weekday as i64) / 7,
+                    2,
+                    b'0',
+                ),
+                b'V' => number(out, self.iso_week().1 as i64, 2, b'0'),
+                b'w' => number(out, self.weekday as i64, 1, b'0'),
+                b'W' => number(
+                    out,
+                    (self.yearday as i64 + 7 - (self.weekday as i64 + 6) % 7) / 7,
+                    2,
+                    b'0',
+                ),
+                b'y' => number(out, self.year.rem_euclid(100), 2, b'0'),
+                b'Y' => number(out, self.year, 1, b'0'),
+                b'z' => {
+                    let sign = if self.offset < 0 { b'-' } else { b'+' };
+                    let offset = self.offset.unsigned_abs();
+                    let (hours, minutes, seconds) = (offset / 3600, offset / 60 % 60, offset % 60);
+                    let two = |value: u32| [b'0' + (value / 10) as u8, b'0' + (value % 10) as u8];
+                    out.push(sign);
+                    out.extend_from_slice(&two(hours));
+                    if colons > 0 {
+                        out.push(b':');
+                    }
+                    out.extend_from_slice(&two(minutes));
+                    if colons > 1 {
+                        out.push(b':');
+                        out.extend_from_slice(&two(seconds));
+                    }
+                }
+                b'Z' => text(out, self.abbreviation),
+                b'c' => self.format(b"%a %b %e %H:%M:%S %Y", out),
+                b'D' | b'x' => self.format(b"%m/%d/%y", out),
+                b'F' => self.format(b"%Y-%m-%d", out),
+                b'r' => self.format(b"%I:%M:%S %p", out),
+                b'R' => self.format(b"%H:%M", out),
+                b'T' | b'X' => self.format(b"%H:%M:%S", out),
+                other => {
+                    out.push(b'%');
+                    out.push(other);
+                }
+            }
+        }
+    }
+
+    /// Returns the ISO 8601 week-based year and week number
+    fn iso_week(&self) -> (i64, u8) {
+        let weekday = (self.weekday as i64 + 6) % 7;
+        let week = (self.yearday as i64 - weekday + 10) / 7;
+
+        let weeks_in = |year: i64| {
+            let jan1 = (days_from_civil(year, 1, 1) + 4).rem_euclid(7);
+            if jan1 == 4 || (jan1 == 3 && is_leap_year(year)) {
+                53
+            } else {
+                52
+            }
+        };
+
+        if week < 1 {
+            (self.year - 1, weeks_in(self.year - 1))
+        } else if week > weeks_in(self.year) as i64 {
+            (self.year + 1, 1)
+        } else {
+            (self.year, week as u8)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LocalTimeType {
+    offset: i32,
+    is_dst: bool,
+    abbreviation: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RuleDate {
+    /// `Jn`, 1 based day of year ignoring February 29th
+    Julian(u16),
+    /// `n`, 0 based day of year counting February 29th
+    Ordinal(u16),
+    /// `Mm.w.d`, day `d` of week `w` of month `m`
+    MonthWeekDay(u8, u8, u8),
+}
+
+impl RuleDate {
+    fn day_of_year(self, year: i64) -> i64 {
+        match self {
+            Self::Julian(day) => {
+                let day = day as i64 - 1;
+                if is_leap_year(year) && day >= 59 {
+                    day + 1
+                } else {
+                    day
+                }
+            }
+            Self::Ordinal(day) => day as i64,
+            Self::MonthWeekDay(month, week, weekday) => {
+                let first = days_from_civil(year, month, 1);
+                let first_weekday = (first + 4).rem_euclid(7);
+                let mut day =
+                    (weekday as i64 - first_weekday).rem_euclid(7) + (week as i64 - 1) * 7;
+                let length = days_in_month(year, month) as i64;
+                while day >= length {
+                    day -= 7;
+                }
+
+                first + day - days_from_civil(year, 1, 1)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DstRule {
+    start: RuleDate,
+    start_time: i32,
+    end: RuleDate,
+    end_time: i32,
+}
+
+/// Time zone described by a POSIX `TZ` string such as `CET-1CEST,M3.5.0,M10.5.0/3`
+#[derive(Debug, Clone)]
+struct PosixTz {
+    std: LocalTimeType,
+    dst: Option<(LocalTimeType, DstRule)>,
+}
+
+impl PosixTz {
+    fn parse(tz: &[u8], abbreviations: &mut Vec<u8>) -> Option<Self> {
+        let mut rest = tz;
+
+        let std_name = parse_tz_name(&mut rest)?;
+        let std_offset = -parse_tz_offset(&mut rest)?;
+
+        let std = LocalTimeType {
+            offset: std_offset,
+            is_dst: false,
+            abbreviation: push_abbreviation(abbreviations, std_name),
+        };
+
+        if rest.is_empty() {
+            return Some(Self { std, dst: None });
+        }
+
+        let dst_name = parse_tz_name(&mut rest)?;
+        let dst_offset = match rest.first() {
+            Some(b',') | None => std_offset + 3600,
+            Some(_) => -parse_tz_offset(&mut rest)?,
+        };
+
+        let dst = LocalTimeType {
+            offset: dst_offset,
+            is_dst: true,
+            abbreviation: push_abbreviation(abbreviations, dst_name),
+        };
+
+        let rule = if rest.is_empty() {
+            DstRule {
+                start: RuleDate::MonthWeekDay(3, 2, 0),
+                start_time: 7200,
+                end: RuleDate::MonthWeekDay(11, 1, 0),
+                end_time: 7200,
+            }
+        } else {
+            rest = rest.strip_prefix(b",")?;
+            let (start, start_time) = parse_rule_date(&mut rest)?;
+            rest = rest.strip_prefix(b",")?;
+            let (end, end_time) = parse_rule_date(&mut rest)?;
+
+            if !rest.is_empty() {
+                return None;
+            }
+
+            DstRule {
+                start,
+                start_time,
+                end,
+                end_time,
+            }
+        };
+
+        Some(Self {
+            std,
+            dst: Some((dst, rule)),
+        })
+    }
+
+    fn local_type_at(&self, timestamp: i64) -> LocalTimeType {
+        let Some((dst, rule)) = self.dst else {
+            return self.std;
+        };
+
+        let (year, ..) =
+            civil_from_days((timestamp + self.std.offset as i64).div_euclid(SECS_PER_DAY));
+        let year_start = days_from_civil(year, 1, 1) * SECS_PER_DAY;
+
+        let start =
+            year_start + rule.start.day_of_year(year) * SECS_PER_DAY + rule.start_time as i64
+                - self.std.offset as i64;
+        let end = year_start + rule.end.day_of_year(year) * SECS_PER_DAY + rule.end_time as i64
+            - dst.offset as i64;
+
+        let in_dst = if start < end {
+            start <= timestamp && timestamp < end
+        } else {
+            !(end <= timestamp && timestamp < start)
+        };
+
+        if in_dst { dst } else { self.std }
+    }
+}
+
+fn push_abbreviation(abbreviations: &mut Vec<u8>, name: &[u8]) -> usize {
+    let index = abbreviations.len();
+    abbreviations.extend_from_slice(name);
+    abbreviations.push(0);
+    index
+}
+
+fn parse_tz_name<'a>(rest: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let (name, remaining) = if let Some(quoted) = rest.strip_prefix(b"<") {
+        let end = quoted.iter().position(|&b| b == b'>')?;
+        (&quoted[..end], &quoted[end + 1..])
+    } else {
+        let end = rest
+            .iter()
+            .position(|b| !b.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        (&rest[..end], &rest[end..])
+    };
+
+    if name.len() < 3 {
+        return None;
+    }
+
+    *rest = remaining;
+    Some(name)
+}
+
+/// Parses `[+-]hh[:mm[:ss]]`, returning the number of seconds
+fn parse_tz_offset(rest: &mut &[u8]) -> Option<i32> {
+    let sign = match rest.first() {
+        Some(b'-') => {
+            *rest = &rest[1..];
+            -1
+        }
+        Some(b'+') => {
+            *rest = &rest[1..];
+            1
+        }
+        _ => 1,
+    };
+
+    let mut total = 0;
+    for (index, multiplier) in [3600, 60, 1].into_iter().enumerate() {
+        if index > 0 {
+            match rest.strip_prefix(b":") {
+                Some(remaining) => *rest = remaining,
+                None => break,
+            }
+        }
+
+        let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 || digits > 3 {
+            return None;
+        }
+
+        let value = rest[..digits]
+            .iter()
+            .fold(0i32, |acc, b| acc * 10 + (b - b'0') as i32);
+        *rest = &rest[digits..];
+        total += value * multiplier;
+    }
+
+    Some(sign * total)
+}
+
+fn parse_rule_date(rest: &mut &[u8]) -> Option<(RuleDate, i32)> {
+    let number = |rest: &mut &[u8]| -> Option<u16> {
+        let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        let value = rest[..digits].iter().try_fold(0u16, |acc, b| {
+            acc.checked_mul(10)?.checked_add((b - b'0') as u16)
+        })?;
+        *rest = &rest[digits..];
+        Some(value)
+    };
+
+    let date = match rest.first()? {
+        b'J' => {
+            *rest = &rest[1..];
+            let day = number(rest)?;
+            (1..=365).contains(&day).then_some(RuleDate::Julian(day))?
+        }
+        b'M' => {
+            *rest = &rest[1..];
+            let month = number(rest)?;
+            *rest = rest.strip_prefix(b".")?;
+            let week = number(rest)?;
+            *rest = rest.strip_prefix(b".")?;
+            let weekday = number(rest)?;
+
+            if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+                return None;
+            }
+
+            RuleDate::MonthWeekDay(month as u8, week as u8, weekday as u8)
+        }
+        _ => {
+            let day = number(rest)?;
+            (day <= 365).then_some(RuleDate::Ordinal(day))?
+        }
+    };
+
+    let time = match rest.strip_prefix(b"/") {
+        Some(remaining) => {
+            *rest = remaining;
+            parse_tz_offset(rest)?
+        }
+        None => 7200,
+    };
+
+    Some((date, time))
+}
+
+/// A time zone, either loaded from a TZif file or described by a POSIX `TZ` string
+#[derive(Debug, Clone)]
+pub struct TimeZone {
+    transitions: Vec<i64>,
+    transition_types: Vec<u8>,
+    types: Vec<LocalTimeType>,
+    abbreviations: Vec<u8>,
+    rule: Option<PosixTz>,
+}
+
+impl TimeZone {
+    pub fn utc() -> Self {
+        let mut abbreviations = Vec::new();
+        let utc = LocalTimeType {
+            offset: 0,
+            is_dst: false,
+            abbreviation: push_abbreviation(&mut abbreviations, b"UTC"),
+        };
+
+        Self {
+            transitions: Vec::new(),
+            transition_types: Vec::new(),
+            types: vec![utc],
+            abbreviations,
+            rule: None,
+        }
+    }
+
+    /// Loads the time zone of the calling process, following the same rules
+    /// as the C library: the `TZ` environment variable if set, `/etc/localtime` otherwise
+    pub fn local() -> Self {
+        match env::var_os("TZ") {
+            None => Self::from_file("/etc/localtime").unwrap_or_else(|_| Self::utc()),
+            Some(tz) => Self::from_tz(std::os::unix::ffi::OsStrExt::as_bytes(tz.as_os_str()))
+                .unwrap_or_else(Self::utc),
+        }
+    }
+
+    /// Interprets a `TZ` value, either as the name of a zoneinfo file or as a POSIX rule
+    pub fn from_tz(tz: &[u8]) -> Option<Self> {
+        if tz.is_empty() {
+            return Some(Self::utc());
+        }
+
+        let tz = tz.strip_prefix(b":").unwrap_or(tz);
+        let name = str::from_utf8(tz).ok();
+
+        if let Some(name) = name
+            && !name.contains("..")
+        {
+            let zone = if name.starts_with('/') {
+                Self::from_file(name)
+            } else {
+                let dir = env::var("TZDIR").unwrap_or_else(|_| "/usr/share/zoneinfo".into());
+                Self::from_file(format!("{dir}/{name}"))
+            };
+
+            if let Ok(zone) = zone {
+                return Some(zone);
+            }
+        }
+
+        let mut abbreviations = Vec::new();
+        let rule = PosixTz::parse(tz, &mut abbreviations)?;
+
+        Some(Self {
+            transitions: Vec::new(),
+            transition_types: Vec::new(),
+            types: vec![rule.std],
+            abbreviations,
+            rule: Some(rule),
+        })
+    }
+
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        Self::from_tzif(&data).ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))
+    }
+
+    /// Parses the contents of a TZif file (RFC 8536)
+    fn from_tzif(data: &[u8]) -> Option<Self> {
+        fn be32(data: &[u8], at: usize) -> Option<i32> {
+            Some(i32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+        }
+
+        fn be64(data: &[u8], at: usize) -> Option<i64> {
+            Some(i64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+        }
+
+        struct Header {
+            isutcnt: usize,
+            isstdcnt: usize,
+            leapcnt: usize,
+            timecnt: usize,
+            typecnt: usize,
+            charcnt: usize,
+        }
+
+        impl Header {
+            fn parse(data: &[u8]) -> Option<(u8, Self)> {
+                if data.get(..4)? != b"TZif" {
+                    return None;
+                }
+
+                let count = |index: usize| be32(data, 20 + index * 4).map(|c| c as usize);
+
+                Some((
+                    *data.get(4)?,
+                    Self {
+                        isutcnt: count(0)?,
+                        isstdcnt: count(1)?,
+                        leapcnt: count(2)?,
+                        timecnt: count(3)?,
+                        typecnt: count(4)?,
+                        charcnt: count(5)?,
+                    },
+                ))
+            }
+
+            fn data_len(&self, time_size: usize) -> usize {
+                self.timecnt * time_size
+                    + self.timecnt
+                    + self.typecnt * 6
+                    + self.charcnt
+                    + self.leapcnt * (time_size + 4)
+                    + self.isstdcnt
+                    + self.isutcnt
+            }
+        }
+
+        const HEADER_LEN: usize = 44;
+
+        let (version, mut header) = Header::parse(data)?;
+        let mut body = HEADER_LEN;
+        let mut time_size = 4;
+
+        if version >= b'2' {
+            let v2_start = HEADER_LEN + header.data_len(4);
+            header = Header::parse(data.get(v2_start..)?)?.1;
+            body = v2_start + HEADER_LEN;
+            time_size = 8;
+        }
+
+        let mut at = body;
+        let mut transitions = Vec::with_capacity(header.timecnt);
+        for _ in 0..header.timecnt {
+            transitions.push(if time_size == 8 {
+                be64(data, at)?
+            } else {
+                be32(data, at)? as i64
+            });
+            at += time_size;
+        }
+
+        let transition_types = data.get(at..at + header.timecnt)?.to_vec();
+        at += header.timecnt;
+
+        let mut types = Vec::with_capacity(header.typecnt);
+        for _ in 0..header.typecnt {
+            types.push(LocalTimeType {
+                offset: be32(data, at)?,
+                is_dst: *data.get(at + 4)? != 0,
+                abbreviation: *data.get(at + 5)? as usize,
+            });
+            at += 6;
+        }
+
+        if types.is_empty() || transition_types.iter().any(|&t| t as usize >= types.len()) {
+            return None;
+        }
+
+        let mut abbreviations = data.get(at..at + header.charcnt)?.to_vec();
+        at += header.data_len(time_size) - (at - body);
+
+        let rule = if version >= b'2' {
+            data.get(at..)
+                .and_then(|footer| footer.strip_prefix(b"\n"))
+                .and_then(|footer| footer.split(|&b| b == b'\n').next())
+                .filter(|footer| !footer.is_empty())
+                .and_then(|footer| PosixTz::parse(footer, &mut abbreviations))
+        } else {
+            None
+        };
+
+        Some(Self {
+            transitions,
+            transition_types,
+            types,
+            abbreviations,
+            rule,
+        })
+    }
+
+    fn local_type_at(&self, timestamp: i64) -> LocalTimeType {
+        if let Some(rule) = &self.rule
+            && self
+                .transitions
+                .last()
+                .is_none_or(|&last| timestamp >= last)
+        {
+            return rule.local_type_at(timestamp);
+        }
+
+        match self.transitions.partition_point(|&t| t <= timestamp) {
+            0 => self.types[0],
+            index => self.types[self.transition_types[index - 1] as usize],
+        }
+    }
+
+    fn abbreviation(&self, local: LocalTimeType) -> &str {
+        let bytes = self
+            .abbreviations
+            .get(local.abbreviation..)
+            .unwrap_or_default();
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        str::from_utf8(&bytes[..end]).unwrap_or_default()
+    }
+
+    /// Returns the offset from UTC in seconds at the given point in time
+    pub fn offset_at(&self, timestamp: i64) -> i32 {
+        self.local_type_at(timestamp).offset
+    }
+
+    /// Returns whether daylight saving time is in effect at the given point in time
+    pub fn is_dst_at(&self, timestamp: i64) -> bool {
+        self.local_type_at(timestamp).is_dst
+    }
+
+    /// Breaks a UNIX timestamp down into local calendar time
+    pub fn to_local(&self, timestamp: i64, nanosecond: u32) -> DateTime<'_> {
+        let local = self.local_type_at(timestamp);
+        let abbreviation = self.abbreviation(local);
+
+        DateTime::from_offset(timestamp, nanosecond, local.offset, abbreviation)
+    }
+
+    /// Converts local calendar time back to a UNIX timestamp, like `mktime`
+    ///
+    /// Times that fall into a gap are resolved using the offset in effect
+    /// before the transition, ambiguous times prefer the earlier instant.
+    pub fn from_local(&self, local_seconds: i64) -> i64 {
+        let mut best = None;
+
+        for guess in [
+            local_seconds - self.offset_at(local_seconds - SECS_PER_DAY) as i64,
+            local_seconds - self.offset_at(local_seconds + SECS_PER_DAY) as i64,
+        ] {
+            if guess + self.offset_at(guess) as i64 == local_seconds {
+                best = Some(best.map_or(guess, |best: i64| best.min(guess)));
+            }
+        }
+
+        best.unwrap_or_else(|| local_seconds - self.offset_at(local_seconds - SECS_PER_DAY) as i64)
+    }
+}
+
+impl<'a> DateTime<'a> {
+    /// Breaks a UNIX timestamp down using a fixed offset from UTC
+    pub fn from_offset(
+        timestamp: i64,
+        nanosecond: u32,
+        offset: i32,
+        abbreviation: &'a str,
+    ) -> Self {
+        let local = timestamp + offset as i64;
+        let days = local.div_euclid(SECS_PER_DAY);
+        let seconds = local.rem_euclid(SECS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: (seconds / 3600) as u8,
+            minute: (seconds / 60 % 60) as u8,
+            second: (seconds % 60) as u8,
+            nanosecond,
+            weekday: (days + 4).rem_euclid(7) as u8,
+            yearday: (days - days_from_civil(year, 1, 1)) as u16,
+            offset,
+            abbreviation,
+            timestamp,
+        }
+    }
+}
@@ -0,0 +1,1009 @@
+//! Parsers for the ways of writing a point in time that utilities take as
+//! arguments: the free form dates of GNU `date -d` and POSIX timestamps
+
+use super::{
+    MONTHS, SECS_PER_DAY, TimeZone, WEEKDAYS, checked_days_from_civil, civil_from_days,
+    days_from_civil, days_in_month,
+};
+
+/// Time zone abbreviations understood anywhere, with their offset in minutes
+const ZONES: [(&str, i64); 36] = [
+    ("gmt", 0),
+    ("ut", 0),
+    ("utc", 0),
+    ("z", 0),
+    ("wet", 0),
+    ("west", 60),
+    ("bst", 60),
+    ("cet", 60),
+    ("cest", 120),
+    ("met", 60),
+    ("mest", 120),
+    ("eet", 120),
+    ("eest", 180),
+    ("msk", 180),
+    ("ist", 330),
+    ("hkt", 480),
+    ("jst", 540),
+    ("kst", 540),
+    ("acst", 570),
+    ("aest", 600),
+    ("aedt", 660),
+    ("nzst", 720),
+    ("nzdt", 780),
+    ("nst", -210),
+    ("ndt", -150),
+    ("ast", -240),
+    ("adt", -180),
+    ("est", -300),
+    ("edt", -240),
+    ("cst", -360),
+    ("cdt", -300),
+    ("mst", -420),
+    ("mdt", -360),
+    ("pst", -480),
+    ("pdt", -420),
+    ("hst", -600),
+];
+
+/// Words that count units of time, with their size in seconds or in months
+const UNITS: [(&str, Unit); 8] = [
+    ("year", Unit::Months(12)),
+    ("month", Unit::Months(1)),
+    ("fortnight", Unit::Days(14)),
+    ("week", Unit::Days(7)),
+    ("day", Unit::Days(1)),
+    ("hour", Unit::Seconds(3600)),
+    ("minute", Unit::Seconds(60)),
+    ("second", Unit::Seconds(1)),
+];
+
+/// Ordinal words, "second" is missing since it's always taken as the unit
+const ORDINALS: [(&str, i64); 14] = [
+    ("last", -1),
+    ("this", 0),
+    ("next", 1),
+    ("first", 1),
+    ("third", 3),
+    ("fourth", 4),
+    ("fifth", 5),
+    ("sixth", 6),
+    ("seventh", 7),
+    ("eighth", 8),
+    ("ninth", 9),
+    ("tenth", 10),
+    ("eleventh", 11),
+    ("twelfth", 12),
+];
+
+#[derive(Clone, Copy)]
+enum Unit {
+    Months(i64),
+    Days(i64),
+    Seconds(i64),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Token<'a> {
+    /// The digits of a number, `sign` is 0 when it was written without one
+    Number {
+        digits: &'a str,
+        sign: i64,
+    },
+    Word(&'a str),
+    Char(u8),
+}
+
+impl Token<'_> {
+    fn is_char(self, c: u8) -> bool {
+        self == Token::Char(c)
+    }
+}
+
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let byte = bytes[i];
+
+        let sign = match byte {
+            b'+' => 1,
+            b'-' => -1,
+            _ => 0,
+        };
+
+        if sign != 0 && bytes.get(i + 1).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+
+        if bytes[i].is_ascii_digit() {
+            let digits_start = i;
+
+            while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+                i += 1;
+            }
+
+            tokens.push(Token::Number {
+                digits: &input[digits_start..i],
+                sign,
+            });
+        } else if byte.is_ascii_alphabetic() {
+            // periods allow abbreviations like `a.m.`
+            while bytes
+                .get(i)
+                .is_some_and(|&b| b.is_ascii_alphabetic() || b == b'.')
+            {
+                i += 1;
+            }
+
+            tokens.push(Token::Word(input[start..i].trim_end_matches('.')));
+        } else {
+            if !byte.is_ascii_whitespace() {
+                tokens.push(Token::Char(byte));
+            }
+
+            i += 1;
+        }
+    }
+
+    tokens
+}
+
+/// Like GNU, dates are limited to the years `struct tm` can hold
+const MIN_YEAR: i64 = i32::MIN as i64 + 1900;
+const MAX_YEAR: i64 = i32::MAX as i64 + 1900;
+
+/// Whether the year of a time in seconds since the epoch is one of them
+fn in_range(seconds: i64) -> bool {
+    let (year, ..) = civil_from_days(seconds.div_euclid(SECS_PER_DAY));
+    (MIN_YEAR..=MAX_YEAR).contains(&year)
+}
+
+/// The value of a number, `None` on overflow
+fn value(digits: &str) -> Option<i64> {
+    digits.parse().ok()
+}
+
+/// Nanoseconds from the digits after a decimal point, extra digits are truncated
+fn fraction(digits: &str) -> u32 {
+    let mut nanos = 0;
+
+    for i in 0..9 {
+        let digit = digits.as_bytes().get(i).map_or(0, |byte| byte - b'0');
+        nanos = nanos * 10 + u32::from(digit);
+    }
+
+    nanos
+}
+
+/// Like GNU, two digit years are between 1969 and 2068
+fn year(digits: &str) -> Option<i64> {
+    let year = value(digits)?;
+
+    Some(match digits.len() {
+        ..=2 if year < 69 => year + 2000,
+        ..=2 => year + 1900,
+        _ => year,
+    })
+}
+
+/// Whether `word` is `name` or its first three letters
+fn is_name(word: &str, name: &str) -> bool {
+    let abbreviated = word.len() == 3 && name.len() > 3;
+
+    word.eq_ignore_ascii_case(name) || abbreviated && word.eq_ignore_ascii_case(&name[..3])
+}
+
+fn month_number(word: &str) -> Option<u8> {
+    let word = if word == "sept" { "sep" } else { word };
+
+    MONTHS
+        .iter()
+        .position(|month| is_name(word, month))
+        .map(|index| index as u8 + 1)
+}
+
+/// The day of the week, 0 for Sunday
+fn weekday_number(word: &str) -> Option<u8> {
+    let word = match word {
+        "tues" => "tue",
+        "wednes" => "wed",
+        "thur" | "thurs" => "thu",
+        word => word,
+    };
+
+    WEEKDAYS
+        .iter()
+        .position(|day| is_name(word, day))
+        .map(|index| index as u8)
+}
+
+fn unit(word: &str) -> Option<Unit> {
+    let word = match word {
+        "min" | "mins" => "minute",
+        "sec" | "secs" => "second",
+        word => word.strip_suffix('s').unwrap_or(word),
+    };
+
+    lookup(&UNITS, word)
+}
+
+fn lookup<T: Copy>(table: &[(&str, T)], word: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|&(_, value)| value)
+}
+
+#[derive(Default)]
+struct Relative {
+    months: i64,
+    days: i64,
+    seconds: i64,
+}
+
+/// What was found in a date, anything missing is taken from the current time
+#[derive(Default)]
+struct Items {
+    /// Year, if given, month and day
+    date: Option<(Option<i64>, u8, u8)>,
+    /// Hours, minutes, seconds and nanoseconds
+    time: Option<(i64, i64, i64, u32)>,
+    /// Seconds east of UTC the date and time are in
+    zone: Option<i64>,
+    /// The ordinal and the day of the week, like `next friday`
+    weekday: Option<(i64, u8)>,
+    relative: Relative,
+    relative_seen: bool,
+}
+
+impl Items {
+    fn set_date(&mut self, year: Option<i64>, month: i64, day: i64) -> Option<()> {
+        let valid = (1..=12).contains(&month)
+            && day >= 1
+            && day <= i64::from(days_in_month(year.unwrap_or(2000), month as u8));
+
+        if !valid || self.date.is_some() {
+            return None;
+        }
+
+        self.date = Some((year, month as u8, day as u8));
+        Some(())
+    }
+
+    fn set_time(&mut self, hour: i64, minute: i64, second: i64, nanos: u32) -> Option<()> {
+        if hour > 23 || minute > 59 || second > 59 || self.time.is_some() {
+            return None;
+        }
+
+        self.time = Some((hour, minute, second, nanos));
+        Some(())
+    }
+
+    fn set_zone(&mut self, offset: i64) -> Option<()> {
+        if offset.abs() > SECS_PER_DAY || self.zone.is_some() {
+            return None;
+        }
+
+        self.zone = Some(offset);
+        Some(())
+    }
+
+    fn add(&mut self, count: i64, unit: Unit) -> Option<()> {
+        let relative = &mut self.relative;
+
+        match unit {
+            Unit::Months(months) => {
+                relative.months = relative.months.checked_add(count.checked_mul(months)?)?
+            }
+            Unit::Days(days) => {
+                relative.days = relative.days.checked_add(count.checked_mul(days)?)?
+            }
+            Unit::Seconds(seconds) => {
+                relative.seconds = relative.seconds.checked_add(count.checked_mul(seconds)?)?
+            }
+        }
+
+        self.relative_seen = true;
+        Some(())
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    items: Items,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self, ahead: usize) -> Option<Token<'a>> {
+        self.tokens.get(self.pos + ahead).copied()
+    }
+
+    fn next_if(&mut self, f: impl FnOnce(Token<'a>) -> bool) -> Option<Token<'a>> {
+        let token = self.peek(0).filter(|&token| f(token))?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn unsigned(&mut self) -> Option<&'a str> {
+        match self.next_if(|token| matches!(token, Token::Number { sign: 0, .. }))? {
+            Token::Number { digits, .. } => Some(digits),
+            _ => None,
+        }
+    }
+
+    fn word(&mut self, f: impl FnOnce(&str) -> bool) -> Option<&'a str> {
+        match self.next_if(|token| matches!(token, Token::Word(word) if f(word)))? {
+            Token::Word(word) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn parse(&mut self) -> Option<()> {
+        while let Some(token) = self.peek(0) {
+            match token {
+                Token::Number { digits, sign } => self.number(digits, sign)?,
+                Token::Word(word) => self.word_item(word)?,
+                _ => return None,
+            }
+        }
+
+        Some(())
+    }
+
+    fn number(&mut self, digits: &'a str, sign: i64) -> Option<()> {
+        let next = self.peek(1);
+
+        if sign == 0 && next.is_some_and(|token| token.is_char(b':')) {
+            return self.time();
+        }
+
+        if let Some(Token::Word(word)) = next
+            && let Some(unit) = unit(word)
+        {
+            self.pos += 2;
+
+            let mut count = value(digits)? * if sign < 0 { -1 } else { 1 };
+
+            if self.word(|word| word == "ago").is_some() {
+                count = -count;
+            }
+
+            return self.items.add(count, unit);
+        }
+
+        if sign != 0 {
+            return None;
+        }
+
+        self.pos += 1;
+
+        match (next, self.peek(1), self.peek(2)) {
+            // ISO 8601 `YYYY-MM-DD`, optionally followed by `THH:MM`
+            (
+                Some(Token::Number {
+                    digits: month,
+                    sign: -1,
+                }),
+                Some(Token::Number {
+                    digits: day,
+                    sign: -1,
+                }),
+                _,
+            ) => {
+                self.pos += 2;
+                self.items
+                    .set_date(Some(year(digits)?), value(month)?, value(day)?)?;
+
+                if self.peek(0) == Some(Token::Word("t"))
+                    && self.peek(2).is_some_and(|token| token.is_char(b':'))
+                {
+                    self.pos += 1;
+                    self.time()?;
+                }
+
+                Some(())
+            }
+            // `MM/DD[/YYYY]` or `YYYY/MM/DD`
+            (
+                Some(Token::Char(b'/')),
+                Some(Token::Number {
+                    digits: second,
+                    sign: 0,
+                }),
+                _,
+            ) => {
+                self.pos += 2;
+
+                let third = match self.peek(0) {
+                    Some(Token::Char(b'/')) => {
+                        self.pos += 1;
+                        Some(self.unsigned()?)
+                    }
+                    _ => None,
+                };
+
+                match third {
+                    Some(third) if digits.len() >= 3 => {
+                        self.items
+                            .set_date(Some(year(digits)?), value(second)?, value(third)?)
+                    }
+                    Some(third) => {
+                        self.items
+                            .set_date(Some(year(third)?), value(digits)?, value(second)?)
+                    }
+                    None => self.items.set_date(None, value(digits)?, value(second)?),
+                }
+            }
+            // `DD month [YYYY]` and `DD-mon-YYYY`
+            (Some(Token::Word(word)), ..)
+            | (Some(Token::Char(b'-')), Some(Token::Word(word)), _)
+                if month_number(word).is_some() =>
+            {
+                let month = month_number(word)?;
+                let dashed = next.is_some_and(|token| token.is_char(b'-'));
+
+                self.pos += if dashed { 2 } else { 1 };
+
+                let year = match self.peek(0) {
+                    Some(Token::Number { digits, sign: -1 }) if dashed => {
+                        self.pos += 1;
+                        Some(year(digits)?)
+                    }
+                    Some(Token::Number { digits, sign: 0 })
+                        if !dashed && !self.peek(1).is_some_and(|token| token.is_char(b':')) =>
+                    {
+                        self.pos += 1;
+                        Some(year(digits)?)
+                    }
+                    _ => None,
+                };
+
+                self.items.set_date(year, i64::from(month), value(digits)?)
+            }
+            // `3pm`
+            (Some(Token::Word(word)), ..) if meridiem(word).is_some() => {
+                self.pos += 1;
+
+                let hour = meridiem_hour(value(digits)?, meridiem(word)?)?;
+                self.items.set_time(hour, 0, 0, 0)?;
+                self.zone_offset()
+            }
+            _ => self.pure_number(digits),
+        }
+    }
+
+    /// A number on its own is a year after a date without one, a date when
+    /// it is long enough to hold one or else the hours and minutes, like GNU
+    fn pure_number(&mut self, digits: &str) -> Option<()> {
+        let number = value(digits)?;
+        let items = &mut self.items;
+
+        if let Some((None, month, day)) = items.date
+            && !items.relative_seen
+            && (items.time.is_some() || digits.len() > 2)
+        {
+            items.date = Some((Some(year(digits)?), month, day));
+            return Some(());
+        }
+
+        if digits.len() > 4 {
+            let year = year(&digits[..digits.len() - 4])?;
+
+            return items.set_date(Some(year), number / 100 % 100, number % 100);
+        }
+
+        let (hour, minute) = match digits.len() {
+            ..=2 => (number, 0),
+            _ => (number / 100, number % 100),
+        };
+
+        items.set_time(hour, minute, 0, 0)
+    }
+
+    /// `HH:MM[:SS[.NNN]]`, then maybe `am`/`pm` and an offset from UTC
+    fn time(&mut self) -> Option<()> {
+        let hour = value(self.unsigned()?)?;
+        self.next_if(|token| token.is_char(b':'))?;
+        let minute = value(self.unsigned()?)?;
+
+        let mut second = 0;
+        let mut nanos = 0;
+
+        if self.next_if(|token| token.is_char(b':')).is_some() {
+            second = value(self.unsigned()?)?;
+
+            if self
+                .next_if(|token| token.is_char(b'.') || token.is_char(b','))
+                .is_some()
+            {
+                nanos = fraction(self.unsigned()?);
+            }
+        }
+
+        let hour = match self.word(|word| meridiem(word).is_some()) {
+            Some(word) => meridiem_hour(hour, meridiem(word)?)?,
+            None => hour,
+        };
+
+        self.items.set_time(hour, minute, second, nanos)?;
+        self.zone_offset()
+    }
+
+    /// `+HH`, `+HHMM` or `+HH:MM`, if there is one
+    fn zone_offset(&mut self) -> Option<()> {
+        let Some(Token::Number { digits, sign }) = self.peek(0) else {
+            return Some(());
+        };
+
+        // the count of a relative item
+        if sign == 0
+            || self
+                .peek(1)
+                .is_some_and(|token| matches!(token, Token::Word(word) if unit(word).is_some()))
+        {
+            return Some(());
+        }
+
+        self.pos += 1;
+
+        let value = value(digits)?;
+
+        let (hours, minutes) = if self.next_if(|token| token.is_char(b':')).is_some() {
+            (value, self::value(self.unsigned()?)?)
+        } else if digits.len() <= 2 {
+            (value, 0)
+        } else {
+            (value / 100, value % 100)
+        };
+
+        if minutes > 59 {
+            return None;
+        }
+
+        self.items.set_zone(sign * (hours * 3600 + minutes * 60))
+    }
+
+    fn word_item(&mut self, word: &'a str) -> Option<()> {
+        self.pos += 1;
+
+        if let Some(month) = month_number(word) {
+            // `month DD[,] [YYYY]`
+            let day = value(self.unsigned()?)?;
+            self.next_if(|token| token.is_char(b','));
+
+            let year = match self.peek(0) {
+                Some(Token::Number { digits, sign: 0 })
+                    if !self.peek(1).is_some_and(|token| token.is_char(b':')) =>
+                {
+                    self.pos += 1;
+                    Some(year(digits)?)
+                }
+                _ => None,
+            };
+
+            return self.items.set_date(year, i64::from(month), day);
+        }
+
+        if let Some(day) = weekday_number(word) {
+            return self.weekday(0, day);
+        }
+
+        if let Some(ordinal) = lookup(&ORDINALS, word) {
+            let next = self.word(|word| unit(word).is_some() || weekday_number(word).is_some())?;
+
+            return match unit(next) {
+                Some(unit) => self.items.add(ordinal, unit),
+                None => self.weekday(ordinal, weekday_number(next)?),
+            };
+        }
+
+        if let Some(unit) = unit(word) {
+            let count = if self.word(|word| word == "ago").is_some() {
+                -1
+            } else {
+                1
+            };
+
+            return self.items.add(count, unit);
+        }
+
+        match word {
+            "now" | "today" => {
+                self.items.relative_seen = true;
+                return Some(());
+            }
+            "yesterday" => return self.items.add(-1, Unit::Days(1)),
+            "tomorrow" => return self.items.add(1, Unit::Days(1)),
+            _ => {}
+        }
+
+        let mut offset = lookup(&ZONES, word)? * 60;
+
+        if self.word(|word| word == "dst").is_some() {
+            offset += 3600;
+        }
+
+        // `UTC+2` and the like
+        if let Some(Token::Number {
+            digits,
+            sign: sign @ (1 | -1),
+        }) = self.peek(0)
+            && !self
+                .peek(1)
+                .is_some_and(|token| matches!(token, Token::Word(word) if unit(word).is_some()))
+        {
+            self.pos += 1;
+
+            let value = value(digits)?;
+            let (hours, minutes) = if self.next_if(|token| token.is_char(b':')).is_some() {
+                (value, self::value(self.unsigned()?)?)
+            } else if digits.len() <= 2 {
+                (value, 0)
+            } else {
+                (value / 100, value % 100)
+            };
+
+            offset += sign * (hours * 3600 + minutes * 60);
+        }
+
+        self.items.set_zone(offset)
+    }
+
+    fn weekday(&mut self, ordinal: i64, day: u8) -> Option<()> {
+        if self.items.weekday.is_some() {
+            return None;
+        }
+
+        self.next_if(|token| token.is_char(b','));
+        self.items.weekday = Some((ordinal, day));
+        Some(())
+    }
+}
+
+fn meridiem(word: &str) -> Option<bool> {
+    match word {
+        "am" | "a.m" => Some(false),
+        "pm" | "p.m" => Some(true),
+        _ => None,
+    }
+}
+
+fn meridiem_hour(hour: i64, pm: bool) -> Option<i64> {
+    if !(1..=12).contains(&hour) {
+        return None;
+    }
+
+    Some(hour % 12 + if pm { 12 } else { 0 })
+}
+
+/// `@SECONDS[.NNN]`, seconds since the epoch
+fn parse_epoch(input: &str) -> Option<(i64, u32)> {
+    let (whole, frac) = match input.split_once(['.', ',']) {
+        Some((whole, frac)) => (whole, frac),
+        None => (input, ""),
+    };
+
+    let digits = whole.strip_prefix(['+', '-']).unwrap_or(whole);
+
+    if digits.is_empty()
+        || !digits.bytes().all(|byte| byte.is_ascii_digit())
+        || !frac.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let seconds: i64 = whole.parse().ok()?;
+    let nanos = fraction(frac);
+
+    // the fraction counts away from zero like the rest of the number
+    let (seconds, nanos) = if whole.starts_with('-') && nanos != 0 {
+        (seconds.checked_sub(1)?, 1_000_000_000 - nanos)
+    } else {
+        (seconds, nanos)
+    };
+
+    in_range(seconds).then_some((seconds, nanos))
+}
+
+/// Splits off a leading `TZ="..."`, which picks the time zone of the rest
+fn split_tz(input: &str) -> Option<(Option<String>, &str)> {
+    let Some(rest) = input.strip_prefix("TZ=\"") else {
+        return Some((None, input));
+    };
+
+    let mut tz = String::new();
+    let mut chars = rest.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((Some(tz), &rest[i + 1..])),
+            '\\' => tz.push(chars.next()?.1),
+            c => tz.push(c),
+        }
+    }
+
+    None
+}
+
+/// Parses a date in the free form of GNU `date -d`: calendar dates such as
+/// `2024-01-31`, `1/31/2024` or `31 jan 2024`, times of day like `13:30:05.25`
+/// or `1pm`, time zones as names or offsets, days of the week and relative
+/// items like `yesterday`, `+3 days` or `next monday`. `@SECONDS` stands for
+/// a number of seconds since the epoch.
+///
+/// Anything not in the date is taken from `now`, seconds and nanoseconds
+/// since the epoch, in `tz`. Like GNU, the time of day is midnight when
+/// only a date or a day of the week is given, or nothing at all.
+pub fn parse_date(input: &str, now: (i64, u32), tz: &TimeZone) -> Option<(i64, u32)> {
+    let (zone_name, input) = split_tz(input.trim())?;
+    let named_zone;
+    let tz = match zone_name {
+        Some(name) => {
+            named_zone = TimeZone::from_tz(name.as_bytes())?;
+            &named_zone
+        }
+        None => tz,
+    };
+
+    let input = input.trim();
+
+    if let Some(epoch) = input.strip_prefix('@') {
+        return parse_epoch(epoch);
+    }
+
+    let lower = input.to_ascii_lowercase();
+    let mut parser = Parser {
+        tokens: tokenize(&lower),
+        pos: 0,
+        items: Items::default(),
+    };
+
+    parser.parse()?;
+
+    let items = parser.items;
+    let current = tz.to_local(now.0, now.1);
+
+    let (year, month, day) = match items.date {
+        Some((year, month, day)) => (year.unwrap_or(current.year), month, day),
+        None => (current.year, current.month, current.day),
+    };
+
+    // a date without a year may be February 29th
+    if day > days_in_month(year, month) {
+        return None;
+    }
+
+    // an empty date is midnight today
+    let midnight = items.date.is_some() || items.weekday.is_some() || input.is_empty();
+    let (hour, minute, second, nanos) = match items.time {
+        Some(time) => time,
+        None if midnight => (0, 0, 0, 0),
+        None => (
+            i64::from(current.hour),
+            i64::from(current.minute),
+            i64::from(current.second),
+            now.1,
+        ),
+    };
+
+    // months past the end of the year and days past the end of the month
+    // carry over like they do with `mktime`
+    let months = (month as i64 - 1).checked_add(items.relative.months)?;
+    let year = year.checked_add(months.div_euclid(12))?;
+    let month = months.rem_euclid(12) as u8 + 1;
+
+    if !(MIN_YEAR..=MAX_YEAR).contains(&year) {
+        return None;
+    }
+
+    let mut days = checked_days_from_civil(year, month, 1)?
+        .checked_add(i64::from(day) - 1)?
+        .checked_add(items.relative.days)?;
+
+    // like GNU, the day of the week only counts without a date
+    if let Some((ordinal, weekday)) = items.weekday
+        && items.date.is_none()
+    {
+        let current = (days + 4).rem_euclid(7);
+        let weekday = i64::from(weekday);
+        let forward = (weekday - current + 7) % 7;
+        let weeks = ordinal - i64::from(ordinal > 0 && current != weekday);
+
+        days = days.checked_add(weeks.checked_mul(7)?.checked_add(forward)?)?;
+    }
+
+    let local = days
+        .checked_mul(SECS_PER_DAY)?
+        .checked_add(hour * 3600 + minute * 60 + second)?;
+
+    if !in_range(local) {
+        return None;
+    }
+
+    let timestamp = match items.zone {
+        Some(offset) => local - offset,
+        None => tz.from_local(local),
+    }
+    .checked_add(items.relative.seconds)?;
+
+    in_range(timestamp).then_some((timestamp, nanos))
+}
+
+/// Parses a POSIX timestamp `[[CC]YY]MMDDhhmm[.ss]` as used by `touch -t`,
+/// in `tz`. The year defaults to the current one at `now`.
+pub fn parse_posix_stamp(stamp: &str, now: i64, tz: &TimeZone) -> Option<i64> {
+    let (digits, second) = match stamp.split_once('.') {
+        Some((digits, second)) if second.len() == 2 => (digits, Some(second)),
+        Some(_) => return None,
+        None => (stamp, None),
+    };
+
+    if !stamp
+        .bytes()
+        .all(|byte| byte.is_ascii_digit() || byte == b'.')
+    {
+        return None;
+    }
+
+    let pair = |at: usize| -> i64 { value(&digits[at..at + 2]).unwrap_or(0) };
+    let (year, rest) = match digits.len() {
+        8 => (tz.to_local(now, 0).year, 0),
+        10 => (self::year(&digits[..2])?, 2),
+        12 => (value(&digits[..4])?, 4),
+        _ => return None,
+    };
+
+    let (month, day, hour, minute) = (pair(rest), pair(rest + 2), pair(rest + 4), pair(rest + 6));
+    let second = second.map_or(Some(0), value)?;
+
+    let valid = (1..=12).contains(&month)
+        && day >= 1
+        && day <= i64::from(days_in_month(year, month as u8))
+        && hour < 24
+        && minute < 60
+        && second <= 60;
+
+    if !valid {
+        return None;
+    }
+
+    // a leap second is the first second of the next minute
+    let local = days_from_civil(year, month as u8, day as u8) * SECS_PER_DAY
+        + hour * 3600
+        + minute * 60
+        + second;
+
+    Some(tz.from_local(local))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tue Nov 14 22:13:20 UTC 2023
+    const NOW: (i64, u32) = (1_700_000_000, 250_000_000);
+
+    fn utc(input: &str) -> Option<i64> {
+        parse_date(input, NOW, &TimeZone::utc()).map(|(seconds, _)| seconds)
+    }
+
+    fn cet(input: &str) -> Option<i64> {
+        let tz = TimeZone::from_tz(b"CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        parse_date(input, NOW, &tz).map(|(seconds, _)| seconds)
+    }
+
+    #[test]
+    fn absolute_dates() {
+        assert_eq!(utc("2024-01-15 10:30:00"), Some(1_705_314_600));
+        assert_eq!(utc("2024-01-15T10:30:00Z"), Some(1_705_314_600));
+        assert_eq!(utc("15 Jan 2024 10:30"), Some(1_705_314_600));
+        assert_eq!(utc("Jan 15 2024"), Some(1_705_276_800));
+        assert_eq!(utc("1/15/2024"), Some(1_705_276_800));
+        assert_eq!(utc("2024-01-15 3pm"), Some(1_705_330_800));
+        assert_eq!(
+            parse_date("@1700000000.5", NOW, &TimeZone::utc()),
+            Some((1_700_000_000, 500_000_000))
+        );
+    }
+
+    #[test]
+    fn missing_parts_come_from_now() {
+        assert_eq!(utc(""), Some(1_699_920_000));
+        assert_eq!(utc("now"), Some(NOW.0));
+        assert_eq!(
+            parse_date("now", NOW, &TimeZone::utc()),
+            Some((NOW.0, NOW.1))
+        );
+        assert_eq!(utc("10:30"), Some(1_699_957_800));
+    }
+
+    #[test]
+    fn relative_dates() {
+        assert_eq!(utc("+1 day"), Some(NOW.0 + 86_400));
+        assert_eq!(utc("-1 hour"), Some(NOW.0 - 3_600));
+        assert_eq!(utc("yesterday"), Some(NOW.0 - 86_400));
+        assert_eq!(utc("2 weeks ago"), Some(NOW.0 - 14 * 86_400));
+        assert_eq!(utc("2024-01-31 +1 month"), Some(1_709_337_600));
+        assert_eq!(utc("2024-03-01 yesterday"), Some(1_709_164_800));
+        assert_eq!(utc("2024-01-15 2 weeks ago"), Some(1_704_067_200));
+        // Friday after Tuesday the 14th
+        assert_eq!(utc("friday"), Some(1_700_179_200));
+    }
+
+    #[test]
+    fn time_zones() {
+        assert_eq!(utc("2024-01-15 10:30 +0200"), Some(1_705_307_400));
+        assert_eq!(cet("2024-01-01 12:00"), Some(1_704_106_800));
+        assert_eq!(cet("2024-07-01 12:00"), Some(1_719_828_000));
+        assert_eq!(cet("2024-07-01 12:00 EST"), Some(1_719_853_200));
+        assert_eq!(cet("TZ=\"UTC\" 2024-07-01 12:00"), Some(1_719_835_200));
+    }
+
+    #[test]
+    fn invalid_dates() {
+        assert_eq!(utc("23:59:60"), None);
+        assert_eq!(utc("25:00"), None);
+        assert_eq!(utc("2024-02-30"), None);
+        assert_eq!(utc("2023-02-29"), None);
+        assert_eq!(utc("13/01/2024"), None);
+        assert_eq!(utc("10:30 11:30"), None);
+        assert_eq!(utc("foo"), None);
+    }
+
+    #[test]
+    fn out_of_range_dates() {
+        assert_eq!(utc("9223372036854775807-01-01"), None);
+        assert_eq!(utc("99999999999-01-01"), None);
+        assert_eq!(utc("2147485548-01-01"), None);
+        assert_eq!(utc("2147485547-12-31 +1 day"), None);
+        assert_eq!(utc("1000000000000000000 seconds"), None);
+        assert_eq!(utc("1000000000000000000 hours"), None);
+        assert_eq!(utc("10000000000000 days"), None);
+        assert_eq!(utc("1000000000000 months"), None);
+        assert_eq!(utc("9223372036854775807 years"), None);
+        assert_eq!(utc("-9223372036854775807 years"), None);
+        assert_eq!(utc("monday 9223372036854775807 weeks"), None);
+        assert_eq!(utc("9223372036854775807 monday"), None);
+        assert_eq!(utc("-9223372036854775807 monday"), None);
+        assert_eq!(utc("@9223372036854775807"), None);
+        assert_eq!(utc("@-9223372036854775808.5"), None);
+        assert_eq!(cet("99999999999-01-01"), None);
+    }
+
+    #[test]
+    fn edges_of_the_range() {
+        assert_eq!(utc("2147485547-01-01"), Some(67_768_036_160_140_800));
+        assert_eq!(
+            utc("2147485547-12-31 23:59:59"),
+            Some(67_768_036_191_676_799)
+        );
+        assert_eq!(utc("@67768036191676799"), Some(67_768_036_191_676_799));
+        assert_eq!(utc("@67768036191676800"), None);
+    }
+
+    #[test]
+    fn posix_stamps() {
+        let tz = TimeZone::utc();
+
+        assert_eq!(
+            parse_posix_stamp("202401010000", NOW.0, &tz),
+            Some(1_704_067_200)
+        );
+        assert_eq!(
+            parse_posix_stamp("2401010000.30", NOW.0, &tz),
+            Some(1_704_067_230)
+        );
+        assert_eq!(
+            parse_posix_stamp("01010000", NOW.0, &tz),
+            Some(1_672_531_200)
+        );
+        assert_eq!(parse_posix_stamp("202402300000", NOW.0, &tz), None);
+        assert_eq!(parse_posix_stamp("2024010100", NOW.0, &tz), None);
+        assert_eq!(parse_posix_stamp("202401010000.5", NOW.0, &tz), None);
+    }
+}
@@ -0,0 +1,213 @@
+//! Characters in UTF-8, the way glibc sees them in a UTF-8 locale
+
+use std::ffi::CStr;
+
+/// Whether the locale picked by `LC_ALL`, `LC_CTYPE` or `LANG` is a UTF-8 one.
+/// Names of locales that aren't installed fall back to `C` like in C programs.
+pub fn locale_is_utf8() -> bool {
+    // SAFETY: `setlocale` races with other threads using the locale, and the
+    // utilities don't start any. `nl_langinfo` returns a valid string that
+    // stays alive until the next `setlocale`.
+    unsafe {
+        libc::setlocale(libc::LC_CTYPE, c"".as_ptr());
+        CStr::from_ptr(libc::nl_langinfo(libc::CODESET)).to_bytes() == b"UTF-8"
+    }
+}
+
+pub enum Decoded {
+    /// A code point and the length of its encoding
+    Char(u32, usize),
+    Invalid,
+    /// The start of a character that needs more bytes
+    Incomplete,
+}
+
+/// Decodes the character at the start of `bytes` like glibc's `mbrtowc`, which
+/// unlike `str` takes sequences of up to 6 bytes and code points past
+/// U+10FFFF. Overlong encodings and surrogates are invalid.
+pub fn decode(bytes: &[u8]) -> Decoded {
+    let (size, lead) = match bytes[0] {
+        byte @ 0..0x80 => return Decoded::Char(u32::from(byte), 1),
+        byte @ 0xc2..0xe0 => (2, byte & 0x1f),
+        byte @ 0xe0..0xf0 => (3, byte & 0x0f),
+        byte @ 0xf0..0xf8 => (4, byte & 0x07),
+        byte @ 0xf8..0xfc => (5, byte & 0x03),
+        byte @ 0xfc..0xfe => (6, byte & 0x01),
+        _ => return Decoded::Invalid,
+    };
+    let mut c = u32::from(lead);
+
+    for i in 1..size {
+        let Some(&byte) = bytes.get(i) else {
+            return Decoded::Incomplete;
+        };
+
+        if byte & 0xc0 != 0x80 {
+            return Decoded::Invalid;
+        }
+
+        c = (c << 6) | u32::from(byte & 0x3f);
+    }
+
+    const SMALLEST: [u32; 7] = [0, 0, 0x80, 0x800, 0x1_0000, 0x20_0000, 0x400_0000];
+
+    if c < SMALLEST[size] || (0xd800..0xe000).contains(&c) {
+        return Decoded::Invalid;
+    }
+
+    Decoded::Char(c, size)
+}
+
+/// Whether a character is printable, like glibc's `iswprint`. glibc also
+/// takes unassigned code points as non-printable, those count as printable
+/// here rather than carrying a table of them.
+pub fn is_print(c: u32) -> bool {
+    let noncharacter = (0xfdd0..=0xfdef).contains(&c) || c & 0xfffe == 0xfffe;
+
+    char::from_u32(c).is_some_and(|c| !c.is_control())
+        && !noncharacter
+        && !matches!(c, 0x2028 | 0x2029)
+}
+
+/// The columns a character takes up on a terminal, like glibc's `wcwidth`
+/// but with a coarser table of the zero width characters
+pub fn width(c: u32) -> usize {
+    if !is_print(c) {
+        return 0;
+    }
+
+    let zero = matches!(
+        c,
+        0x0300..=0x036f
+            | 0x0483..=0x0489
+            | 0x0591..=0x05bd
+            | 0x0610..=0x061a
+            | 0x064b..=0x065f
+            | 0x1160..=0x11ff
+            | 0x1ab0..=0x1aff
+            | 0x1dc0..=0x1dff
+            | 0x200b..=0x200f
+            | 0x202a..=0x202e
+            | 0x2060..=0x2064
+            | 0x20d0..=0x20ff
+            | 0xfe00..=0xfe0f
+            | 0xfe20..=0xfe2f
+            | 0xfeff
+            | 0xe0000..=0xe0fff
+    );
+
+    // East Asian wide and fullwidth characters
+    let wide = matches!(
+        c,
+        0x1100..=0x115f
+            | 0x231a..=0x231b
+            | 0x2329..=0x232a
+            | 0x23e9..=0x23ec
+            | 0x2614..=0x2615
+            | 0x26aa..=0x26ab
+            | 0x2e80..=0x303e
+            | 0x3041..=0x33ff
+            | 0x3400..=0x4dbf
+            | 0x4e00..=0x9fff
+            | 0xa000..=0xa4cf
+            | 0xa960..=0xa97f
+            | 0xac00..=0xd7a3
+            | 0xf900..=0xfaff
+            | 0xfe10..=0xfe19
+            | 0xfe30..=0xfe6f
+            | 0xff00..=0xff60
+            | 0xffe0..=0xffe6
+            | 0x16fe0..=0x18cff
+            | 0x1b000..=0x1b2ff
+            | 0x1f004
+            | 0x1f0cf
+            | 0x1f18e
+            | 0x1f191..=0x1f19a
+            | 0x1f200..=0x1f251
+            | 0x1f300..=0x1f64f
+            | 0x1f680..=0x1f6ff
+            | 0x1f900..=0x1f9ff
+            | 0x1fa70..=0x1faff
+            | 0x20000..=0x2fffd
+            | 0x30000..=0x3fffd
+    );
+
+    match (zero, wide) {
+        (true, _) => 0,
+        (_, true) => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn char_of(bytes: &[u8]) -> Option<(u32, usize)> {
+        match decode(bytes) {
+            Decoded::Char(c, len) => Some((c, len)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn valid_characters() {
+        assert_eq!(char_of(b"a"), Some((0x61, 1)));
+        assert_eq!(char_of(b"\0"), Some((0, 1)));
+        assert_eq!(char_of("é!".as_bytes()), Some((0xe9, 2)));
+        assert_eq!(char_of("€".as_bytes()), Some((0x20ac, 3)));
+        assert_eq!(char_of("🦀".as_bytes()), Some((0x1f980, 4)));
+        // glibc goes past U+10FFFF
+        assert_eq!(char_of(b"\xf4\x90\x80\x80"), Some((0x11_0000, 4)));
+        assert_eq!(char_of(b"\xf8\x88\x80\x80\x80"), Some((0x20_0000, 5)));
+        assert_eq!(char_of(b"\xfc\x84\x80\x80\x80\x80"), Some((0x400_0000, 6)));
+    }
+
+    #[test]
+    fn invalid_characters() {
+        for bytes in [
+            &b"\x80"[..],
+            b"\xbf",
+            b"\xfe",
+            b"\xff",
+            // overlong
+            b"\xc0\x80",
+            b"\xc1\xbf",
+            b"\xe0\x80\x80",
+            b"\xf0\x80\x80\x80",
+            // surrogates
+            b"\xed\xa0\x80",
+            b"\xed\xbf\xbf",
+            // not continued
+            b"\xc3\x28",
+            b"\xe2\x82\x28",
+            b"\xf0\x9f\xa6a",
+        ] {
+            assert!(matches!(decode(bytes), Decoded::Invalid), "{bytes:x?}");
+        }
+    }
+
+    #[test]
+    fn truncated_characters() {
+        for bytes in [
+            &b"\xc3"[..],
+            b"\xe2",
+            b"\xe2\x82",
+            b"\xf0\x9f\xa6",
+            b"\xfc\x84\x80\x80\x80",
+        ] {
+            assert!(matches!(decode(bytes), Decoded::Incomplete), "{bytes:x?}");
+        }
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(width(u32::from('a')), 1);
+        assert_eq!(width(0x0301), 0);
+        assert_eq!(width(u32::from('日')), 2);
+        assert_eq!(width(0x1f980), 2);
+        assert_eq!(width(0x07), 0);
+        assert!(!is_print(0x2028));
+        assert!(!is_print(0xfffe));
+    }
+}